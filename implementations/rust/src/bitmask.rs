@@ -0,0 +1,286 @@
+use crate::number::Number;
+use crate::smart_backtrack::{Cell, Coord, Grid, SolveError};
+
+/// Bits 0 to 8 set, one for each number.
+const ALL: u16 = 0b111111111;
+
+/// The box containing each cell index.
+const BOX_OF: [usize; 81] = {
+    let mut boxes = [0; 81];
+    let mut i = 0;
+    while i < 81 {
+        boxes[i] = (i / 27) * 3 + (i % 9) / 3;
+        i += 1;
+    }
+    boxes
+};
+
+/// A grid stored as plain digits, with the numbers used in every row,
+/// column and box kept as `u16` masks that are updated as cells are filled,
+/// so checking a number is a single AND.
+struct Board {
+    /// The number in each cell, or 0 if it is empty.
+    digits: [u8; 81],
+    rows: [u16; 9],
+    cols: [u16; 9],
+    boxes: [u16; 9],
+    /// Indices of the cells that were empty in the puzzle. The first
+    /// `depth` of them are filled on the current search path.
+    empty: Vec<usize>,
+    nodes: u64,
+}
+impl Board {
+    /// Build a board from a grid, or return `None` if two of its fixed
+    /// cells clash.
+    fn from_grid(grid: &Grid) -> Option<Self> {
+        let mut board = Self {
+            digits: [0; 81],
+            rows: [0; 9],
+            cols: [0; 9],
+            boxes: [0; 9],
+            empty: Vec::with_capacity(81),
+            nodes: 0,
+        };
+
+        for i in 0..81 {
+            match grid.get(Coord::from_row_col(i as u8 / 9, i as u8 % 9)) {
+                Cell::Fixed(num) => {
+                    let bit = 1 << (num.to_u8() - 1);
+                    if board.candidates(i) & bit == 0 {
+                        return None;
+                    }
+                    board.place(i, bit);
+                }
+                Cell::Empty(_) => board.empty.push(i),
+            }
+        }
+
+        Some(board)
+    }
+
+    fn candidates(&self, i: usize) -> u16 {
+        !(self.rows[i / 9] | self.cols[i % 9] | self.boxes[BOX_OF[i]]) & ALL
+    }
+
+    fn place(&mut self, i: usize, bit: u16) {
+        self.digits[i] = bit.trailing_zeros() as u8 + 1;
+        self.rows[i / 9] |= bit;
+        self.cols[i % 9] |= bit;
+        self.boxes[BOX_OF[i]] |= bit;
+    }
+
+    fn remove(&mut self, i: usize, bit: u16) {
+        self.digits[i] = 0;
+        self.rows[i / 9] &= !bit;
+        self.cols[i % 9] &= !bit;
+        self.boxes[BOX_OF[i]] &= !bit;
+    }
+
+    /// Fill the empty cells from `depth` onwards, always branching on the
+    /// cell with the fewest candidates. Each solution is passed to `found`,
+    /// and the search stops once it returns `false`.
+    fn search(&mut self, depth: usize, found: &mut impl FnMut(&[u8; 81]) -> bool) -> bool {
+        if depth == self.empty.len() {
+            return found(&self.digits);
+        }
+
+        let mut best = depth;
+        let mut best_count = u32::MAX;
+        for (pos, i) in self.empty.iter().enumerate().skip(depth) {
+            let count = self.candidates(*i).count_ones();
+            if count < best_count {
+                best = pos;
+                best_count = count;
+                if count <= 1 {
+                    break;
+                }
+            }
+        }
+        self.empty.swap(depth, best);
+
+        let i = self.empty[depth];
+        let mut candidates = self.candidates(i);
+        while candidates != 0 {
+            let bit = candidates & candidates.wrapping_neg();
+            candidates &= !bit;
+
+            self.nodes += 1;
+            self.place(i, bit);
+            let keep_going = self.search(depth + 1, found);
+            self.remove(i, bit);
+
+            if !keep_going {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    /// Collect up to `limit` solutions.
+    fn solutions(&mut self, limit: usize) -> Vec<[u8; 81]> {
+        let mut solutions = Vec::new();
+
+        if limit > 0 {
+            self.search(0, &mut |digits| {
+                solutions.push(*digits);
+                solutions.len() < limit
+            });
+        }
+
+        solutions
+    }
+}
+
+pub fn solve(grid: &mut Grid) -> Result<(), SolveError> {
+    solve_counting_nodes(grid, &mut 0)
+}
+
+/// Solve the puzzle, adding the number of search nodes visited (numbers
+/// tentatively placed in a cell) to `nodes`.
+pub fn solve_counting_nodes(grid: &mut Grid, nodes: &mut u64) -> Result<(), SolveError> {
+    match find_solutions(grid, 1, nodes)?.first() {
+        Some(digits) => {
+            fill(grid, digits);
+            Ok(())
+        }
+        None => Err(SolveError::NoSolutionFound),
+    }
+}
+
+/// Like `solve_counting_nodes`, but fails with
+/// `SolveError::MultipleSolutions` if the puzzle does not have exactly one
+/// solution.
+pub fn solve_strict_counting_nodes(grid: &mut Grid, nodes: &mut u64) -> Result<(), SolveError> {
+    match find_solutions(grid, 2, nodes)?.as_slice() {
+        [digits] => {
+            fill(grid, digits);
+            Ok(())
+        }
+        [] => Err(SolveError::NoSolutionFound),
+        _ => Err(SolveError::MultipleSolutions),
+    }
+}
+
+/// Count the solutions of the puzzle, stopping once `limit` have been
+/// found.
+pub fn count_solutions(grid: &Grid, limit: usize) -> usize {
+    match Board::from_grid(grid) {
+        Some(mut board) => board.solutions(limit).len(),
+        None => 0,
+    }
+}
+
+fn find_solutions(grid: &Grid, limit: usize, nodes: &mut u64) -> Result<Vec<[u8; 81]>, SolveError> {
+    let Some(mut board) = Board::from_grid(grid) else {
+        return Err(SolveError::IllegalPuzzle);
+    };
+
+    if !grid.has_sufficient_hints() {
+        return Err(SolveError::TooFewHints);
+    }
+
+    let solutions = board.solutions(limit);
+    *nodes += board.nodes;
+    Ok(solutions)
+}
+
+fn fill(grid: &mut Grid, digits: &[u8; 81]) {
+    for (i, digit) in digits.iter().enumerate() {
+        let coord = Coord::from_row_col(i as u8 / 9, i as u8 % 9);
+        grid.set(coord, Cell::Fixed(Number::from_u8_normalize(*digit)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dlx;
+    use crate::puzzles;
+
+    fn empty() -> Grid {
+        Grid::try_from_line_str(&".".repeat(81)).unwrap()
+    }
+
+    #[test]
+    fn test_box_of() {
+        assert_eq!(BOX_OF[0], 0);
+        assert_eq!(BOX_OF[8], 2);
+        assert_eq!(BOX_OF[30], 4);
+        assert_eq!(BOX_OF[80], 8);
+        for (i, box_) in BOX_OF.iter().enumerate() {
+            let coord = Coord::from_row_col(i as u8 / 9, i as u8 % 9);
+            assert_eq!(*box_, coord.parent_box() as usize);
+        }
+    }
+
+    #[test]
+    fn test_masks() {
+        let grid = Grid::try_from_csv_str(puzzles::OK).unwrap();
+        let mut board = Board::from_grid(&grid).unwrap();
+
+        assert_eq!(board.empty.len(), 81 - 24);
+        // Row 0 holds 1, 2 and 4, column 0 holds 1, 5 and 6, and box 0
+        // holds 5 and 8.
+        assert_eq!(board.rows[0], 0b000001011);
+        assert_eq!(board.candidates(0), 0b101000100);
+
+        board.place(0, 0b100);
+        assert_eq!(board.digits[0], 3);
+        assert_eq!(board.candidates(1) & 0b100, 0);
+        board.remove(0, 0b100);
+        assert_eq!(board.candidates(0), 0b101000100);
+    }
+
+    #[test]
+    fn test_solve() {
+        let mut grid = Grid::try_from_csv_str(puzzles::OK).unwrap();
+        let mut expected = grid;
+        dlx::solve(&mut expected).unwrap();
+
+        let mut nodes = 0;
+        solve_counting_nodes(&mut grid, &mut nodes).unwrap();
+        assert_eq!(grid, expected);
+        assert!(nodes > 0);
+
+        let mut grid = Grid::try_from_csv_str(puzzles::OK).unwrap();
+        solve_strict_counting_nodes(&mut grid, &mut 0).unwrap();
+        assert_eq!(grid, expected);
+    }
+
+    #[test]
+    fn test_count_solutions() {
+        let grid = Grid::try_from_csv_str(puzzles::OK).unwrap();
+        assert_eq!(count_solutions(&grid, 10), 1);
+        assert_eq!(count_solutions(&empty(), 50), 50);
+        assert_eq!(count_solutions(&empty(), 0), 0);
+
+        // Clearing the top two rows of a solution leaves the same number of
+        // solutions for every solver.
+        let mut puzzle = grid;
+        solve(&mut puzzle).unwrap();
+        for col in 0..9 {
+            puzzle.set(Coord::from_row_col(0, col), Cell::init_empty());
+            puzzle.set(Coord::from_row_col(1, col), Cell::init_empty());
+        }
+        assert_eq!(
+            count_solutions(&puzzle, 100),
+            dlx::count_solutions(&puzzle, 100)
+        );
+        assert!(matches!(
+            solve_strict_counting_nodes(&mut puzzle, &mut 0),
+            Err(SolveError::MultipleSolutions)
+        ));
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut grid = empty();
+        assert!(matches!(solve(&mut grid), Err(SolveError::TooFewHints)));
+
+        let mut grid = Grid::try_from_csv_str(puzzles::OK).unwrap();
+        grid.set(Coord::FIRST, Cell::Fixed(Number::Four));
+        assert!(matches!(solve(&mut grid), Err(SolveError::IllegalPuzzle)));
+        assert_eq!(count_solutions(&grid, 10), 0);
+    }
+}
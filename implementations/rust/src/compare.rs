@@ -0,0 +1,549 @@
+use crate::report::{Record, RECORD_FIELDS};
+use crate::smart_backtrack::SolveStats;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+/// Per-puzzle records from a previously saved benchmark run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Baseline {
+    pub records: Vec<Record>,
+    pub input: Option<String>,
+}
+impl Baseline {
+    /// Load a result file written with `--format json` or `--format csv`.
+    pub fn load(path: &Path) -> Result<Self, BaselineError> {
+        let contents = fs::read_to_string(path).map_err(BaselineError::Io)?;
+        Self::parse(&contents)
+    }
+
+    pub fn parse(contents: &str) -> Result<Self, BaselineError> {
+        if contents.trim_start().starts_with('{') {
+            parse_json(contents)
+        } else {
+            parse_csv(contents)
+        }
+    }
+}
+
+fn parse_json(contents: &str) -> Result<Baseline, BaselineError> {
+    let mut records = Vec::new();
+    let mut input = None;
+
+    for (i, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let malformed = |message| BaselineError::Malformed {
+            line: i + 1,
+            message,
+        };
+        let fields = parse_json_object(line).map_err(malformed)?;
+        let get = |key: &str| {
+            fields
+                .get(key)
+                .map(String::as_str)
+                .ok_or_else(|| format!("missing field {:?}", key))
+        };
+
+        match get("type").map_err(malformed)? {
+            "puzzle" => records.push(record_from_fields(get).map_err(malformed)?),
+            "summary" => input = fields.get("input").cloned(),
+            _ => {}
+        }
+    }
+
+    Ok(Baseline { records, input })
+}
+
+fn parse_csv(contents: &str) -> Result<Baseline, BaselineError> {
+    let mut lines = contents.lines().enumerate();
+    let malformed = |line: usize, message: String| BaselineError::Malformed {
+        line: line + 1,
+        message,
+    };
+
+    let header = match lines.next() {
+        Some((_, header)) => split_csv_line(header),
+        None => return Err(malformed(0, String::from("empty file"))),
+    };
+    let mut columns = HashMap::new();
+    for name in RECORD_FIELDS {
+        match header.iter().position(|key| key == name) {
+            Some(column) => columns.insert(name, column),
+            None => return Err(malformed(0, format!("missing column {:?}", name))),
+        };
+    }
+
+    let mut records = Vec::new();
+    for (i, line) in lines.by_ref() {
+        if line.trim().is_empty() {
+            break;
+        }
+
+        let values = split_csv_line(line);
+        let get = |key: &str| {
+            columns
+                .get(key)
+                .and_then(|column| values.get(*column))
+                .map(String::as_str)
+                .ok_or_else(|| String::from("missing value"))
+        };
+        let record = record_from_fields(get).map_err(|message| malformed(i, message))?;
+        records.push(record);
+    }
+
+    let input = match (lines.next(), lines.next()) {
+        (Some((_, keys)), Some((_, values))) => {
+            let keys = split_csv_line(keys);
+            let values = split_csv_line(values);
+            keys.iter()
+                .position(|key| key == "input")
+                .and_then(|column| values.get(column).cloned())
+        }
+        _ => None,
+    };
+
+    Ok(Baseline { records, input })
+}
+
+/// Build a record from the fields named in `RECORD_FIELDS`, looked up with
+/// `get`.
+fn record_from_fields<'a>(get: impl Fn(&str) -> Result<&'a str, String>) -> Result<Record, String> {
+    let number = |name: &str| {
+        let value = get(name)?;
+        value
+            .parse::<u64>()
+            .map_err(|_| format!("invalid {} {:?}", name, value))
+    };
+    // Solvers that only count nodes write the other stats as null, which
+    // JSON leaves out and CSV leaves empty.
+    let search_stats = get("backtracks").is_ok_and(|value| !value.is_empty());
+    let search = |name: &str| if search_stats { number(name) } else { Ok(0) };
+
+    Ok(Record {
+        solver: get("solver")?.to_string(),
+        index: number("index")? as usize,
+        clues: number("clues")? as usize,
+        time: Duration::from_nanos(number("time_ns")?),
+        result: get("result")?.to_string(),
+        stats: SolveStats {
+            nodes: number("nodes")?,
+            backtracks: search("backtracks")?,
+            max_depth: search("max_depth")? as usize,
+            prune_calls: search("prune_calls")?,
+            prune_parents_calls: search("prune_parents_calls")?,
+            propagated: search("propagated")? as usize,
+            guessed: search("guessed")? as usize,
+        },
+        search_stats,
+    })
+}
+
+/// Parse a single-level JSON object, as written by `Report`, into its fields.
+/// Every value is returned as a string; `null` values are left out.
+fn parse_json_object(line: &str) -> Result<HashMap<String, String>, String> {
+    let mut chars = line.trim().chars().peekable();
+    let mut fields = HashMap::new();
+
+    fn skip_whitespace(chars: &mut std::iter::Peekable<std::str::Chars>) {
+        while chars.next_if(|char| char.is_whitespace()).is_some() {}
+    }
+
+    fn parse_string(chars: &mut std::iter::Peekable<std::str::Chars>) -> Result<String, String> {
+        if chars.next() != Some('"') {
+            return Err(String::from("expected a string"));
+        }
+
+        let mut output = String::new();
+        loop {
+            match chars.next() {
+                Some('"') => return Ok(output),
+                Some('\\') => match chars.next() {
+                    Some('n') => output.push('\n'),
+                    Some('r') => output.push('\r'),
+                    Some('t') => output.push('\t'),
+                    Some('u') => {
+                        let hex: String = chars.by_ref().take(4).collect();
+                        let char = u32::from_str_radix(&hex, 16)
+                            .ok()
+                            .and_then(char::from_u32)
+                            .ok_or_else(|| format!("invalid escape \\u{}", hex))?;
+                        output.push(char);
+                    }
+                    Some(char) => output.push(char),
+                    None => return Err(String::from("unterminated string")),
+                },
+                Some(char) => output.push(char),
+                None => return Err(String::from("unterminated string")),
+            }
+        }
+    }
+
+    if chars.next() != Some('{') {
+        return Err(String::from("expected an object"));
+    }
+
+    loop {
+        skip_whitespace(&mut chars);
+        if chars.next_if_eq(&'}').is_some() {
+            break;
+        }
+
+        let key = parse_string(&mut chars)?;
+        skip_whitespace(&mut chars);
+        if chars.next() != Some(':') {
+            return Err(format!("expected ':' after {:?}", key));
+        }
+        skip_whitespace(&mut chars);
+
+        let value = if chars.peek() == Some(&'"') {
+            Some(parse_string(&mut chars)?)
+        } else {
+            let mut literal = String::new();
+            while let Some(char) = chars.next_if(|char| !matches!(char, ',' | '}')) {
+                literal.push(char);
+            }
+            match literal.trim() {
+                "" => return Err(format!("missing value for {:?}", key)),
+                "null" => None,
+                literal => Some(literal.to_string()),
+            }
+        };
+        if let Some(value) = value {
+            fields.insert(key, value);
+        }
+
+        skip_whitespace(&mut chars);
+        match chars.next() {
+            Some(',') => continue,
+            Some('}') => break,
+            _ => return Err(String::from("expected ',' or '}'")),
+        }
+    }
+
+    Ok(fields)
+}
+
+/// Split a CSV line into fields, handling quoted fields.
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(char) = chars.next() {
+        match char {
+            '"' if quoted && chars.next_if_eq(&'"').is_some() => field.push('"'),
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            char => field.push(char),
+        }
+    }
+    fields.push(field);
+
+    fields
+}
+
+/// How one puzzle's solve time changed between two runs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PuzzleChange {
+    pub solver: String,
+    pub index: usize,
+    pub baseline: Duration,
+    pub current: Duration,
+}
+impl PuzzleChange {
+    /// Percentage change in solve time; positive values are slowdowns.
+    pub fn change_pct(&self) -> f64 {
+        change_pct(self.baseline, self.current)
+    }
+}
+
+/// A comparison of the puzzles solved in both a baseline and a current run.
+/// Puzzles are matched by solver and index.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comparison {
+    pub puzzles: Vec<PuzzleChange>,
+    /// Puzzles whose clue count or result differ between the two runs.
+    pub mismatched: usize,
+    /// Puzzles that are only present in one of the runs.
+    pub unmatched: usize,
+    pub threshold_pct: f64,
+}
+impl Comparison {
+    pub fn new(baseline: &[Record], current: &[Record], threshold_pct: f64) -> Self {
+        let by_key: HashMap<(&str, usize), &Record> = baseline
+            .iter()
+            .map(|record| ((record.solver.as_str(), record.index), record))
+            .collect();
+        let mut puzzles = Vec::new();
+        let mut mismatched = 0;
+        let mut matched = 0;
+
+        for record in current.iter() {
+            let Some(old) = by_key.get(&(record.solver.as_str(), record.index)) else {
+                continue;
+            };
+            matched += 1;
+
+            if old.clues != record.clues || old.result != record.result {
+                mismatched += 1;
+            } else if record.is_solved() {
+                puzzles.push(PuzzleChange {
+                    solver: record.solver.clone(),
+                    index: record.index,
+                    baseline: old.time,
+                    current: record.time,
+                });
+            }
+        }
+
+        Self {
+            puzzles,
+            mismatched,
+            unmatched: baseline.len() + current.len() - 2 * matched,
+            threshold_pct,
+        }
+    }
+
+    pub fn baseline_total(&self) -> Duration {
+        self.puzzles.iter().map(|change| change.baseline).sum()
+    }
+
+    pub fn current_total(&self) -> Duration {
+        self.puzzles.iter().map(|change| change.current).sum()
+    }
+
+    /// Percentage change in total solve time; positive values are slowdowns.
+    pub fn change_pct(&self) -> f64 {
+        change_pct(self.baseline_total(), self.current_total())
+    }
+
+    /// Whether no puzzle was solved in both runs, leaving nothing to compare.
+    pub fn is_empty(&self) -> bool {
+        self.puzzles.is_empty()
+    }
+
+    /// Whether the total solve time got slower by more than the threshold.
+    pub fn is_regression(&self) -> bool {
+        self.change_pct() > self.threshold_pct
+    }
+
+    fn count(&self, predicate: impl Fn(f64) -> bool) -> usize {
+        self.puzzles
+            .iter()
+            .filter(|change| predicate(change.change_pct()))
+            .count()
+    }
+}
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let threshold = self.threshold_pct;
+        let slower: Vec<&PuzzleChange> = self
+            .puzzles
+            .iter()
+            .filter(|change| change.change_pct() > threshold)
+            .collect();
+
+        for change in slower.iter() {
+            writeln!(
+                f,
+                "{} puzzle {}: {:.3?} -> {:.3?} ({:+.1}%)",
+                change.solver,
+                change.index,
+                change.baseline,
+                change.current,
+                change.change_pct()
+            )?;
+        }
+
+        writeln!(f, "compared:   {}", self.puzzles.len())?;
+        writeln!(f, "faster:     {}", self.count(|pct| pct < -threshold))?;
+        writeln!(f, "slower:     {}", slower.len())?;
+        writeln!(
+            f,
+            "unchanged:  {}",
+            self.count(|pct| pct.abs() <= threshold)
+        )?;
+        if self.mismatched > 0 {
+            writeln!(f, "mismatched: {}", self.mismatched)?;
+        }
+        if self.unmatched > 0 {
+            writeln!(f, "unmatched:  {}", self.unmatched)?;
+        }
+        writeln!(
+            f,
+            "total:      {:.3?} -> {:.3?} ({:+.1}%, speedup {:.3}x)",
+            self.baseline_total(),
+            self.current_total(),
+            self.change_pct(),
+            self.baseline_total().as_secs_f64() / self.current_total().as_secs_f64()
+        )?;
+
+        if self.is_regression() {
+            write!(
+                f,
+                "REGRESSION: slower than baseline by more than {}%",
+                threshold
+            )
+        } else {
+            write!(f, "OK: within {}% of baseline", threshold)
+        }
+    }
+}
+
+fn change_pct(baseline: Duration, current: Duration) -> f64 {
+    (current.as_secs_f64() / baseline.as_secs_f64() - 1.0) * 100.0
+}
+
+#[derive(Debug)]
+pub enum BaselineError {
+    Io(io::Error),
+    Malformed { line: usize, message: String },
+}
+impl fmt::Display for BaselineError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "{}", err),
+            Self::Malformed { line, message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+impl std::error::Error for BaselineError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::{Format, Metadata, Report};
+    use crate::smart_backtrack::SolveError;
+
+    fn records(times_us: &[u64]) -> Vec<Record> {
+        times_us
+            .iter()
+            .enumerate()
+            .map(|(i, us)| {
+                let stats = SolveStats {
+                    nodes: 7,
+                    backtracks: 2,
+                    max_depth: 3,
+                    prune_calls: 1,
+                    prune_parents_calls: 7,
+                    propagated: 54,
+                    guessed: 3,
+                };
+                let time = Duration::from_micros(*us);
+                Record::new("dlx", i + 1, 24, time, &Ok(()), stats, true)
+            })
+            .collect()
+    }
+
+    fn saved(records: Vec<Record>, format: Format) -> String {
+        let metadata = Metadata {
+            input: String::from("corpus.txt"),
+            git_revision: Some(String::from("abc1234")),
+            profile: "release",
+            cpus: 1,
+            timestamp: 0,
+        };
+        let mut out = Vec::new();
+        Report::new(records, metadata)
+            .write(format, &mut out)
+            .unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut records = records(&[10, 20]);
+        records.push(Record::new(
+            "dlx",
+            3,
+            12,
+            Duration::from_micros(1),
+            &Err(SolveError::TooFewHints),
+            SolveStats::default(),
+            false,
+        ));
+
+        for format in [Format::Json, Format::Csv] {
+            let baseline = Baseline::parse(&saved(records.clone(), format)).unwrap();
+            assert_eq!(baseline.records, records);
+            assert_eq!(baseline.input.as_deref(), Some("corpus.txt"));
+        }
+    }
+
+    #[test]
+    fn test_malformed() {
+        let result = Baseline::parse("{\"type\":\"puzzle\",\"index\":1}\n");
+        assert!(matches!(
+            result,
+            Err(BaselineError::Malformed { line: 1, .. })
+        ));
+
+        let result = Baseline::parse("solver,index,clues\ndlx,1,2\n");
+        assert!(matches!(result, Err(BaselineError::Malformed { .. })));
+    }
+
+    #[test]
+    fn test_comparison() {
+        let baseline = records(&[100, 100, 100, 100]);
+        let current = records(&[50, 100, 104, 200]);
+        let comparison = Comparison::new(&baseline, &current, 5.0);
+
+        assert_eq!(comparison.puzzles.len(), 4);
+        assert_eq!(comparison.baseline_total(), Duration::from_micros(400));
+        assert_eq!(comparison.current_total(), Duration::from_micros(454));
+        assert!((comparison.change_pct() - 13.5).abs() < 1e-9);
+        assert!(comparison.is_regression());
+        assert_eq!(comparison.count(|pct| pct > 5.0), 1);
+        assert_eq!(comparison.count(|pct| pct < -5.0), 1);
+
+        let comparison = Comparison::new(&baseline, &current, 20.0);
+        assert!(!comparison.is_regression());
+    }
+
+    #[test]
+    fn test_comparison_mismatches() {
+        let baseline = records(&[100, 100, 100]);
+        let mut current = records(&[100, 100]);
+        current[1].clues = 30;
+
+        let comparison = Comparison::new(&baseline, &current, 5.0);
+        assert_eq!(comparison.puzzles.len(), 1);
+        assert_eq!(comparison.mismatched, 1);
+        assert_eq!(comparison.unmatched, 1);
+
+        // The same puzzle run by another solver is not compared.
+        let mut current = records(&[100]);
+        current[0].solver = String::from("bitmask");
+        let comparison = Comparison::new(&baseline, &current, 5.0);
+        assert!(comparison.puzzles.is_empty());
+        assert_eq!(comparison.unmatched, 4);
+    }
+
+    #[test]
+    fn test_comparison_nothing_in_common() {
+        let baseline = records(&[100, 100]);
+
+        // Other puzzles run by the same solver.
+        let mut current = records(&[100, 100, 100, 100]);
+        current.drain(..2);
+        let comparison = Comparison::new(&baseline, &current, 5.0);
+        assert!(comparison.is_empty());
+        assert_eq!(comparison.unmatched, 4);
+
+        // The same puzzles run by another solver.
+        let mut current = records(&[100, 100]);
+        for record in &mut current {
+            record.solver = String::from("bitmask");
+        }
+        let comparison = Comparison::new(&baseline, &current, 5.0);
+        assert!(comparison.is_empty());
+        assert_eq!(comparison.unmatched, 4);
+    }
+}
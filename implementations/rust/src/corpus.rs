@@ -0,0 +1,196 @@
+use crate::smart_backtrack::{Grid, ParseError};
+use std::fmt;
+use std::io::{self, BufRead};
+
+/// Reads puzzles one at a time from a corpus file.
+///
+/// A corpus holds any mix of single-line puzzles (81 characters each) and
+/// 9-line CSV blocks separated by blank lines. Lines starting with `#` are
+/// comments.
+pub struct CorpusReader<R> {
+    lines: io::Lines<R>,
+    line: usize,
+    record: usize,
+    lenient: bool,
+    done: bool,
+}
+impl<R: BufRead> CorpusReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            lines: reader.lines(),
+            line: 0,
+            record: 0,
+            lenient: false,
+            done: false,
+        }
+    }
+
+    /// Parse CSV blocks with `Grid::from_csv_str_lenient` instead of
+    /// rejecting malformed ones.
+    pub fn lenient(mut self, lenient: bool) -> Self {
+        self.lenient = lenient;
+        self
+    }
+
+    fn next_line(&mut self) -> Option<io::Result<String>> {
+        let line = self.lines.next()?;
+        self.line += 1;
+        Some(line)
+    }
+
+    fn parse_csv_block(&mut self, first: String) -> Result<Grid, CorpusError> {
+        let start = self.line;
+        let mut block = first;
+        block.push('\n');
+
+        loop {
+            match self.next_line() {
+                Some(Ok(line)) if line.trim().is_empty() => break,
+                Some(Ok(line)) => {
+                    block.push_str(&line);
+                    block.push('\n');
+                }
+                Some(Err(err)) => return Err(CorpusError::Io(err)),
+                None => break,
+            }
+        }
+
+        if self.lenient {
+            Ok(Grid::from_csv_str_lenient(&block))
+        } else {
+            Grid::try_from_csv_str(&block).map_err(|error| CorpusError::Parse {
+                record: self.record,
+                line: start,
+                error,
+            })
+        }
+    }
+}
+impl<R: BufRead> Iterator for CorpusReader<R> {
+    type Item = Result<Grid, CorpusError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        loop {
+            let line = match self.next_line()? {
+                Ok(line) => line,
+                Err(err) => {
+                    self.done = true;
+                    return Some(Err(CorpusError::Io(err)));
+                }
+            };
+            let trimmed = line.trim();
+
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+
+            self.record += 1;
+
+            let result = if trimmed.contains(',') {
+                self.parse_csv_block(line)
+            } else {
+                Grid::try_from_line_str(trimmed).map_err(|error| CorpusError::Parse {
+                    record: self.record,
+                    line: self.line,
+                    error,
+                })
+            };
+
+            if let Err(CorpusError::Io(_)) = result {
+                self.done = true;
+            }
+
+            return Some(result);
+        }
+    }
+}
+
+/// Errors produced while reading a corpus. Records and lines are numbered
+/// from 1, as in a text editor.
+#[derive(Debug)]
+pub enum CorpusError {
+    Io(io::Error),
+    Parse {
+        record: usize,
+        line: usize,
+        error: ParseError,
+    },
+}
+impl fmt::Display for CorpusError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "read error: {}", err),
+            Self::Parse {
+                record,
+                line,
+                error,
+            } => write!(f, "puzzle {} (line {}): {}", record, line, error),
+        }
+    }
+}
+impl std::error::Error for CorpusError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::puzzles;
+
+    const LINE: &str = concat!(
+        "000002104008001003500060090090080046600700000",
+        "100000080037200019000000030000090000"
+    );
+
+    #[test]
+    fn test_mixed_formats() {
+        let input = format!("# corpus\n{}\n\n{}\n{}\n", LINE, puzzles::OK, LINE);
+        let grids: Vec<Grid> = CorpusReader::new(input.as_bytes())
+            .map(Result::unwrap)
+            .collect();
+
+        let expected = Grid::try_from_csv_str(puzzles::OK).unwrap();
+        assert_eq!(grids, vec![expected; 3]);
+    }
+
+    #[test]
+    fn test_errors_are_reported_per_record() {
+        let input = format!("{}\n123\n{}\n", LINE, LINE);
+        let results: Vec<_> = CorpusReader::new(input.as_bytes()).collect();
+
+        assert_eq!(results.len(), 3);
+        assert!(results[0].is_ok());
+        assert!(results[2].is_ok());
+
+        match &results[1] {
+            Err(CorpusError::Parse {
+                record,
+                line,
+                error,
+            }) => {
+                assert_eq!((*record, *line), (2, 2));
+                assert_eq!(*error, ParseError::WrongLineLength { len: 3 });
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_csv_block_errors_point_to_block_start() {
+        let bad = puzzles::OK.replacen("5,0,0", "5,x,0", 1);
+        let input = format!("{}\n\n{}", LINE, bad);
+        let results: Vec<_> = CorpusReader::new(input.as_bytes()).collect();
+
+        match &results[1] {
+            Err(CorpusError::Parse { record, line, .. }) => {
+                assert_eq!((*record, *line), (2, 3));
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        let lenient: Vec<_> = CorpusReader::new(input.as_bytes()).lenient(true).collect();
+        assert!(lenient[1].is_ok());
+    }
+}
@@ -0,0 +1,370 @@
+use crate::number::Number;
+use crate::smart_backtrack::{Cell, Coord, Grid, SolveError};
+
+/// Columns of the exact-cover matrix: one per cell, plus one per number in
+/// each row, column and box.
+const COLUMNS: usize = 4 * 81;
+
+/// Index of the root header node.
+const ROOT: usize = 0;
+
+/// A candidate placement of a number in a cell, i.e. a row of the matrix.
+#[derive(Debug, Clone, Copy)]
+struct Candidate {
+    coord: Coord,
+    num: Number,
+}
+
+/// Knuth's Algorithm X over a sparse exact-cover matrix stored as dancing
+/// links. Node 0 is the root, nodes 1 to 324 are the column headers, and
+/// the nodes after them are the 1s of the matrix.
+struct Dlx {
+    left: Vec<usize>,
+    right: Vec<usize>,
+    up: Vec<usize>,
+    down: Vec<usize>,
+    /// The column header each node belongs to.
+    column: Vec<usize>,
+    /// The candidate each node belongs to. Unused for header nodes.
+    candidate: Vec<usize>,
+    /// The number of nodes in each column, indexed by header node.
+    size: Vec<usize>,
+    candidates: Vec<Candidate>,
+    /// The puzzle the matrix was built from.
+    grid: Grid,
+    /// The candidates chosen on the current search path.
+    chosen: Vec<usize>,
+    nodes: u64,
+}
+impl Dlx {
+    /// Build the matrix for a grid. Fixed cells contribute a single
+    /// candidate; empty cells contribute one per possible number.
+    fn new(grid: &Grid) -> Self {
+        let headers = COLUMNS + 1;
+        let mut dlx = Self {
+            left: (0..headers).map(|i| (i + headers - 1) % headers).collect(),
+            right: (0..headers).map(|i| (i + 1) % headers).collect(),
+            up: (0..headers).collect(),
+            down: (0..headers).collect(),
+            column: (0..headers).collect(),
+            candidate: vec![0; headers],
+            size: vec![0; headers],
+            candidates: Vec::with_capacity(729),
+            grid: *grid,
+            chosen: Vec::with_capacity(81),
+            nodes: 0,
+        };
+
+        for index in 0..81 {
+            let coord = Coord::from_row_col(index / 9, index % 9);
+            match grid.get(coord) {
+                Cell::Fixed(num) => dlx.add_candidate(Candidate { coord, num }),
+                Cell::Empty(possible_nums) => {
+                    for num in possible_nums.iter() {
+                        dlx.add_candidate(Candidate { coord, num });
+                    }
+                }
+            }
+        }
+
+        dlx
+    }
+
+    fn add_candidate(&mut self, candidate: Candidate) {
+        let id = self.candidates.len();
+        self.candidates.push(candidate);
+
+        let coord = candidate.coord;
+        let num = candidate.num.to_u8() as usize - 1;
+        let columns = [
+            coord.parent_row() as usize * 9 + coord.parent_col() as usize,
+            81 + coord.parent_row() as usize * 9 + num,
+            162 + coord.parent_col() as usize * 9 + num,
+            243 + coord.parent_box() as usize * 9 + num,
+        ];
+
+        let first = self.left.len();
+        for (i, column) in columns.into_iter().enumerate() {
+            let header = column + 1;
+            let node = first + i;
+
+            self.left.push(if i == 0 { first + 3 } else { node - 1 });
+            self.right.push(if i == 3 { first } else { node + 1 });
+            self.up.push(self.up[header]);
+            self.down.push(header);
+            self.column.push(header);
+            self.candidate.push(id);
+
+            let last = self.up[header];
+            self.down[last] = node;
+            self.up[header] = node;
+            self.size[header] += 1;
+        }
+    }
+
+    fn cover(&mut self, header: usize) {
+        self.right[self.left[header]] = self.right[header];
+        self.left[self.right[header]] = self.left[header];
+
+        let mut row = self.down[header];
+        while row != header {
+            let mut node = self.right[row];
+            while node != row {
+                self.down[self.up[node]] = self.down[node];
+                self.up[self.down[node]] = self.up[node];
+                self.size[self.column[node]] -= 1;
+                node = self.right[node];
+            }
+            row = self.down[row];
+        }
+    }
+
+    fn uncover(&mut self, header: usize) {
+        let mut row = self.up[header];
+        while row != header {
+            let mut node = self.left[row];
+            while node != row {
+                self.size[self.column[node]] += 1;
+                self.down[self.up[node]] = node;
+                self.up[self.down[node]] = node;
+                node = self.left[node];
+            }
+            row = self.up[row];
+        }
+
+        self.right[self.left[header]] = header;
+        self.left[self.right[header]] = header;
+    }
+
+    /// The uncovered column with the fewest nodes, or `None` if every
+    /// column is covered.
+    fn choose_column(&self) -> Option<usize> {
+        let mut best: Option<usize> = None;
+        let mut header = self.right[ROOT];
+
+        while header != ROOT {
+            if best.is_none_or(|best| self.size[header] < self.size[best]) {
+                best = Some(header);
+                if self.size[header] == 0 {
+                    break;
+                }
+            }
+            header = self.right[header];
+        }
+
+        best
+    }
+
+    /// Search for exact covers, calling `found` with the chosen candidates
+    /// of each one. Stops once `found` returns `false`.
+    fn search(&mut self, found: &mut impl FnMut(&Dlx) -> bool) -> bool {
+        let Some(header) = self.choose_column() else {
+            return found(self);
+        };
+
+        self.cover(header);
+
+        let mut row = self.down[header];
+        while row != header {
+            self.nodes += 1;
+            self.chosen.push(self.candidate[row]);
+
+            let mut node = self.right[row];
+            while node != row {
+                self.cover(self.column[node]);
+                node = self.right[node];
+            }
+
+            let keep_going = self.search(found);
+
+            let mut node = self.left[row];
+            while node != row {
+                self.uncover(self.column[node]);
+                node = self.left[node];
+            }
+            self.chosen.pop();
+
+            if !keep_going {
+                self.uncover(header);
+                return false;
+            }
+            row = self.down[row];
+        }
+
+        self.uncover(header);
+        true
+    }
+
+    /// The grid described by the candidates on the current search path.
+    fn solution(&self) -> Grid {
+        let mut grid = self.grid;
+
+        for id in self.chosen.iter() {
+            let candidate = self.candidates[*id];
+            grid.set(candidate.coord, Cell::Fixed(candidate.num));
+        }
+
+        grid
+    }
+
+    /// Collect up to `limit` solutions.
+    fn solutions(&mut self, limit: usize) -> Vec<Grid> {
+        let mut solutions = Vec::new();
+
+        if limit > 0 {
+            self.search(&mut |dlx| {
+                solutions.push(dlx.solution());
+                solutions.len() < limit
+            });
+        }
+
+        solutions
+    }
+}
+
+pub fn solve(grid: &mut Grid) -> Result<(), SolveError> {
+    solve_counting_nodes(grid, &mut 0)
+}
+
+/// Solve the puzzle, adding the number of search nodes visited (candidates
+/// tentatively chosen) to `nodes`.
+pub fn solve_counting_nodes(grid: &mut Grid, nodes: &mut u64) -> Result<(), SolveError> {
+    let solutions = find_solutions(grid, 1, nodes)?;
+
+    match solutions.first() {
+        Some(solved) => {
+            *grid = *solved;
+            Ok(())
+        }
+        None => Err(SolveError::NoSolutionFound),
+    }
+}
+
+/// Like `solve_counting_nodes`, but fails with
+/// `SolveError::MultipleSolutions` if the puzzle does not have exactly one
+/// solution.
+pub fn solve_strict_counting_nodes(grid: &mut Grid, nodes: &mut u64) -> Result<(), SolveError> {
+    let solutions = find_solutions(grid, 2, nodes)?;
+
+    match solutions.as_slice() {
+        [solved] => {
+            *grid = *solved;
+            Ok(())
+        }
+        [] => Err(SolveError::NoSolutionFound),
+        _ => Err(SolveError::MultipleSolutions),
+    }
+}
+
+/// Count the solutions of the puzzle, stopping once `limit` have been
+/// found.
+pub fn count_solutions(grid: &Grid, limit: usize) -> usize {
+    if grid.is_legal() {
+        Dlx::new(grid).solutions(limit).len()
+    } else {
+        0
+    }
+}
+
+fn find_solutions(grid: &Grid, limit: usize, nodes: &mut u64) -> Result<Vec<Grid>, SolveError> {
+    if !grid.is_legal() {
+        Err(SolveError::IllegalPuzzle)
+    } else if !grid.has_sufficient_hints() {
+        Err(SolveError::TooFewHints)
+    } else {
+        let mut dlx = Dlx::new(grid);
+        let solutions = dlx.solutions(limit);
+        *nodes += dlx.nodes;
+        Ok(solutions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::puzzles;
+
+    fn empty() -> Grid {
+        Grid::try_from_line_str(&".".repeat(81)).unwrap()
+    }
+
+    #[test]
+    fn test_matrix() {
+        let mut grid = empty();
+        grid.set(Coord::FIRST, Cell::Fixed(Number::Five));
+        let dlx = Dlx::new(&grid);
+
+        assert_eq!(dlx.candidates.len(), 1 + 80 * 9);
+        assert_eq!(dlx.left.len(), COLUMNS + 1 + 4 * dlx.candidates.len());
+        // The first cell's column only holds the given number.
+        assert_eq!(dlx.size[1], 1);
+        assert_eq!(dlx.size[2], 9);
+        // Five in row 0 can only come from the first cell's candidate.
+        assert_eq!(dlx.size[1 + 81 + 4], 9);
+    }
+
+    #[test]
+    fn test_solve() {
+        let mut grid = Grid::try_from_csv_str(puzzles::OK).unwrap();
+        let mut expected = grid;
+        expected.solve_strict().unwrap();
+
+        let mut nodes = 0;
+        solve_counting_nodes(&mut grid, &mut nodes).unwrap();
+        assert_eq!(grid, expected);
+        assert!(nodes > 0);
+    }
+
+    #[test]
+    fn test_solve_strict() {
+        let mut grid = Grid::try_from_csv_str(puzzles::OK).unwrap();
+        assert!(solve_strict_counting_nodes(&mut grid, &mut 0).is_ok());
+
+        // Swapping the first two rows of the top band keeps a valid grid,
+        // so clearing the cells that differ leaves two solutions.
+        let solved = grid;
+        let mut puzzle = grid;
+        for col in 0..9 {
+            let top = Coord::from_row_col(0, col);
+            let below = Coord::from_row_col(1, col);
+            if solved.get(top) != solved.get(below) {
+                puzzle.set(top, Cell::init_empty());
+                puzzle.set(below, Cell::init_empty());
+            }
+        }
+
+        assert!(count_solutions(&puzzle, 10) >= 2);
+        assert!(matches!(
+            solve_strict_counting_nodes(&mut puzzle, &mut 0),
+            Err(SolveError::MultipleSolutions)
+        ));
+        assert!(solve(&mut puzzle).is_ok());
+        assert!(puzzle.is_legal());
+    }
+
+    #[test]
+    fn test_count_solutions() {
+        let grid = Grid::try_from_csv_str(puzzles::OK).unwrap();
+        assert_eq!(count_solutions(&grid, 10), 1);
+        assert_eq!(count_solutions(&empty(), 50), 50);
+        assert_eq!(count_solutions(&empty(), 0), 0);
+
+        let solutions = Dlx::new(&empty()).solutions(20);
+        for (i, solution) in solutions.iter().enumerate() {
+            assert!(solution.is_legal());
+            assert_eq!(solution.clue_count(), 81);
+            assert!(!solutions[i + 1..].contains(solution));
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut grid = empty();
+        assert!(matches!(solve(&mut grid), Err(SolveError::TooFewHints)));
+
+        let mut grid = Grid::try_from_csv_str(puzzles::OK).unwrap();
+        grid.set(Coord::FIRST, Cell::Fixed(Number::Four));
+        assert!(matches!(solve(&mut grid), Err(SolveError::IllegalPuzzle)));
+        assert_eq!(count_solutions(&grid, 10), 0);
+    }
+}
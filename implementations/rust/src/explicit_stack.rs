@@ -0,0 +1,256 @@
+//! Smart backtracking that keeps the cells being branched on in an explicit
+//! stack instead of recursing.
+//!
+//! Despite its name, the Roc `SmartBacktrackStack.roc` is not this search:
+//! it recurses just like `SmartBacktrack.roc`, keeping possible numbers in a
+//! bitset, which is what `smart_backtrack` already does. The recursive
+//! smart-backtrack solver is the counterpart of both Roc modules in
+//! cross-language benchmarks, and this one measures what giving up
+//! recursion costs.
+
+use crate::possible_nums::PossibleNumsIterator;
+use crate::smart_backtrack::{Cell, Coord, Grid, Propagation, SolveError};
+
+/// An empty cell being branched on, with the candidates not yet tried.
+struct Frame {
+    grid: Grid,
+    coord: Coord,
+    candidates: PossibleNumsIterator,
+}
+
+pub fn solve(grid: &mut Grid, propagation: Propagation) -> Result<(), SolveError> {
+    solve_counting_nodes(grid, propagation, &mut 0)
+}
+
+/// Solve the puzzle, propagating with `propagation` and adding the number
+/// of search nodes visited (numbers tentatively placed in a cell) to
+/// `nodes`.
+pub fn solve_counting_nodes(
+    grid: &mut Grid,
+    propagation: Propagation,
+    nodes: &mut u64,
+) -> Result<(), SolveError> {
+    check(grid)?;
+    match solutions(grid, propagation, 1, nodes).pop() {
+        Some(solved) => {
+            *grid = solved;
+            Ok(())
+        }
+        None => Err(SolveError::NoSolutionFound),
+    }
+}
+
+/// Like `solve_counting_nodes`, but keeps searching after the first
+/// solution and fails with `SolveError::MultipleSolutions` if it finds a
+/// second.
+pub fn solve_strict_counting_nodes(
+    grid: &mut Grid,
+    propagation: Propagation,
+    nodes: &mut u64,
+) -> Result<(), SolveError> {
+    check(grid)?;
+    match solutions(grid, propagation, 2, nodes)[..] {
+        [solved] => {
+            *grid = solved;
+            Ok(())
+        }
+        [] => Err(SolveError::NoSolutionFound),
+        _ => Err(SolveError::MultipleSolutions),
+    }
+}
+
+/// Count the solutions of the puzzle, stopping once `limit` have been
+/// found.
+pub fn count_solutions(grid: &Grid, propagation: Propagation, limit: usize) -> usize {
+    solutions(grid, propagation, limit, &mut 0).len()
+}
+
+fn check(grid: &Grid) -> Result<(), SolveError> {
+    if !grid.is_legal() {
+        Err(SolveError::IllegalPuzzle)
+    } else if !grid.has_sufficient_hints() {
+        Err(SolveError::TooFewHints)
+    } else {
+        Ok(())
+    }
+}
+
+/// Up to `limit` solutions of the puzzle, or none if it is not legal.
+fn solutions(grid: &Grid, propagation: Propagation, limit: usize, nodes: &mut u64) -> Vec<Grid> {
+    let mut pruned = *grid;
+    if !grid.is_legal() || pruned.prune_with(propagation).is_err() {
+        return Vec::new();
+    }
+    search(&pruned, propagation, limit, nodes)
+}
+
+/// The same search as `smart_backtrack`'s recursive `solve_helper`, keeping
+/// the cells being branched on in an explicit stack instead, and carrying
+/// on after each solution until `limit` have been found.
+fn search(grid: &Grid, propagation: Propagation, limit: usize, nodes: &mut u64) -> Vec<Grid> {
+    let mut solutions = Vec::new();
+    let mut stack = Vec::with_capacity(81);
+
+    match next_empty(grid, Coord::FIRST) {
+        Some(frame) => stack.push(frame),
+        // Propagation alone solved the puzzle.
+        None if limit > 0 => solutions.push(*grid),
+        None => {}
+    }
+
+    while solutions.len() < limit {
+        let Some(frame) = stack.last_mut() else {
+            break;
+        };
+        let Some(num) = frame.candidates.next() else {
+            stack.pop();
+            continue;
+        };
+
+        if frame.grid.number_is_legal(frame.coord, num) {
+            *nodes += 1;
+            let coord = frame.coord;
+            let mut new_grid = frame.grid;
+            new_grid.set(coord, Cell::Fixed(num));
+
+            if let Some(next_coord) = coord.next() {
+                if new_grid.prune_parents(&coord, propagation).is_err() {
+                    continue;
+                }
+                if let Some(frame) = next_empty(&new_grid, next_coord) {
+                    stack.push(frame);
+                    continue;
+                }
+            }
+            solutions.push(new_grid);
+        }
+    }
+
+    solutions
+}
+
+/// A frame for the first empty cell at or after `c`, or `None` if every
+/// remaining cell is fixed.
+fn next_empty(grid: &Grid, c: Coord) -> Option<Frame> {
+    let mut coord = c;
+
+    loop {
+        match grid.get(coord) {
+            Cell::Fixed(_) => coord = coord.next()?,
+            Cell::Empty(possible_nums) => {
+                return Some(Frame {
+                    grid: *grid,
+                    coord,
+                    candidates: possible_nums.iter(),
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::puzzles;
+    use crate::smart_backtrack::{Branching, SolveStats};
+
+    fn assert_matches_recursive(input: &str) {
+        assert_matches_recursive_with(input, Propagation::default());
+    }
+
+    fn assert_matches_recursive_with(input: &str, propagation: Propagation) {
+        let puzzle = Grid::try_from_line_str(input).unwrap();
+
+        let mut recursive = puzzle;
+        let mut recursive_stats = SolveStats::default();
+        let recursive_result =
+            recursive.solve_with_stats(Branching::Linear, propagation, &mut recursive_stats);
+
+        let mut stack = puzzle;
+        let mut stack_nodes = 0;
+        let stack_result = solve_counting_nodes(&mut stack, propagation, &mut stack_nodes);
+
+        assert_eq!(
+            format!("{:?}", stack_result),
+            format!("{:?}", recursive_result)
+        );
+        assert_eq!(stack, recursive);
+        assert_eq!(stack_nodes, recursive_stats.nodes);
+    }
+
+    #[test]
+    fn test_matches_recursive() {
+        let ok = Grid::try_from_csv_str(puzzles::OK).unwrap().format_line();
+        assert_matches_recursive(&ok);
+
+        // Dropping clues gives puzzles with several solutions, where both
+        // solvers should still settle on the same one.
+        assert_matches_recursive(&ok.replacen('2', ".", 2));
+        assert_matches_recursive(&ok.replacen('9', ".", 3));
+
+        assert_matches_recursive(concat!(
+            "..3.2.6..9..3.5..1..18.64....81.29..7.......8..67.82....",
+            "26.95..8..2.3..9..5.1.3.."
+        ));
+    }
+
+    #[test]
+    fn test_matches_recursive_with_propagation() {
+        let ok = Grid::try_from_csv_str(puzzles::OK).unwrap().format_line();
+        let propagation = Propagation {
+            hidden_singles: true,
+            subsets: true,
+            intersections: true,
+        };
+        assert_matches_recursive_with(&ok, propagation);
+        assert_matches_recursive_with(&ok.replacen('9', ".", 3), propagation);
+    }
+
+    #[test]
+    fn test_solve_strict() {
+        let puzzle = Grid::try_from_csv_str(puzzles::OK).unwrap();
+        let mut expected = puzzle;
+        expected.solve_strict().unwrap();
+
+        let mut grid = puzzle;
+        let mut nodes = 0;
+        solve_strict_counting_nodes(&mut grid, Propagation::default(), &mut nodes).unwrap();
+        assert_eq!(grid, expected);
+        assert_eq!(count_solutions(&puzzle, Propagation::default(), 10), 1);
+
+        // The strict search carries on past the first solution.
+        let mut solve_nodes = 0;
+        let mut grid = puzzle;
+        solve_counting_nodes(&mut grid, Propagation::default(), &mut solve_nodes).unwrap();
+        assert!(nodes > solve_nodes);
+
+        let several = Grid::try_from_line_str(&puzzle.format_line().replacen('9', ".", 3)).unwrap();
+        let mut grid = several;
+        assert!(matches!(
+            solve_strict_counting_nodes(&mut grid, Propagation::default(), &mut 0),
+            Err(SolveError::MultipleSolutions)
+        ));
+        assert_eq!(grid, several);
+        assert_eq!(
+            count_solutions(&several, Propagation::default(), 100),
+            several.count_solutions(100)
+        );
+        assert_eq!(count_solutions(&several, Propagation::default(), 0), 0);
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut too_few = Grid::try_from_line_str(&".".repeat(81)).unwrap();
+        assert!(matches!(
+            solve(&mut too_few, Propagation::default()),
+            Err(SolveError::TooFewHints)
+        ));
+
+        let mut illegal = Grid::try_from_csv_str(puzzles::OK).unwrap();
+        illegal.set(Coord::FIRST, Cell::Fixed(crate::number::Number::Four));
+        assert!(matches!(
+            solve(&mut illegal, Propagation::default()),
+            Err(SolveError::IllegalPuzzle)
+        ));
+    }
+}
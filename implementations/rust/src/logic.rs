@@ -0,0 +1,2863 @@
+use crate::number::Number;
+use crate::possible_nums::PossibleNums;
+use crate::smart_backtrack::{box_coords, col_coords, row_coords, Cell, Coord, Grid};
+use std::collections::VecDeque;
+use std::fmt;
+
+/// A logical deduction a human solver can make, ordered from easiest to
+/// hardest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Technique {
+    NakedSingle,
+    HiddenSingle,
+    Pointing,
+    BoxLineReduction,
+    NakedPair,
+    HiddenPair,
+    NakedTriple,
+    HiddenTriple,
+    NakedQuad,
+    HiddenQuad,
+    XWing,
+    Swordfish,
+    Jellyfish,
+    XyWing,
+    XyzWing,
+    WWing,
+    UniqueRectangle1,
+    UniqueRectangle2,
+    UniqueRectangle3,
+    UniqueRectangle4,
+    BugPlusOne,
+    ColorTrap,
+    ColorWrap,
+    XChain,
+    XyChain,
+    Aic,
+    AlsXz,
+    DeathBlossom,
+    CellForcingChain,
+    UnitForcingChain,
+}
+impl Technique {
+    pub fn name(self) -> &'static str {
+        match self {
+            Technique::NakedSingle => "Naked Single",
+            Technique::HiddenSingle => "Hidden Single",
+            Technique::Pointing => "Pointing",
+            Technique::BoxLineReduction => "Box/Line Reduction",
+            Technique::NakedPair => "Naked Pair",
+            Technique::HiddenPair => "Hidden Pair",
+            Technique::NakedTriple => "Naked Triple",
+            Technique::HiddenTriple => "Hidden Triple",
+            Technique::NakedQuad => "Naked Quad",
+            Technique::HiddenQuad => "Hidden Quad",
+            Technique::XWing => "X-Wing",
+            Technique::Swordfish => "Swordfish",
+            Technique::Jellyfish => "Jellyfish",
+            Technique::XyWing => "XY-Wing",
+            Technique::XyzWing => "XYZ-Wing",
+            Technique::WWing => "W-Wing",
+            Technique::UniqueRectangle1 => "Unique Rectangle Type 1",
+            Technique::UniqueRectangle2 => "Unique Rectangle Type 2",
+            Technique::UniqueRectangle3 => "Unique Rectangle Type 3",
+            Technique::UniqueRectangle4 => "Unique Rectangle Type 4",
+            Technique::BugPlusOne => "BUG+1",
+            Technique::ColorTrap => "Color Trap",
+            Technique::ColorWrap => "Color Wrap",
+            Technique::XChain => "X-Chain",
+            Technique::XyChain => "XY-Chain",
+            Technique::Aic => "AIC",
+            Technique::AlsXz => "ALS-XZ",
+            Technique::DeathBlossom => "Death Blossom",
+            Technique::CellForcingChain => "Cell Forcing Chain",
+            Technique::UnitForcingChain => "Unit Forcing Chain",
+        }
+    }
+
+    fn naked(size: usize) -> Technique {
+        match size {
+            2 => Technique::NakedPair,
+            3 => Technique::NakedTriple,
+            _ => Technique::NakedQuad,
+        }
+    }
+
+    fn hidden(size: usize) -> Technique {
+        match size {
+            2 => Technique::HiddenPair,
+            3 => Technique::HiddenTriple,
+            _ => Technique::HiddenQuad,
+        }
+    }
+
+    fn fish(size: usize) -> Technique {
+        match size {
+            2 => Technique::XWing,
+            3 => Technique::Swordfish,
+            _ => Technique::Jellyfish,
+        }
+    }
+}
+
+impl fmt::Display for Technique {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// A number filled into a cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Placement {
+    pub coord: Coord,
+    pub num: Number,
+}
+
+impl fmt::Display for Placement {
+    /// Formats the placement as `r<row>c<col>=<num>`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}={}", self.coord, self.num.to_char())
+    }
+}
+
+/// A number removed from the possible numbers of a cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Elimination {
+    pub coord: Coord,
+    pub num: Number,
+}
+
+impl Elimination {
+    /// Remove the number from the possible numbers of the cell.
+    fn apply(&self, grid: &mut Grid) {
+        if let Cell::Empty(mut possible_nums) = grid.get(self.coord) {
+            possible_nums.remove(self.num);
+            grid.set(self.coord, Cell::Empty(possible_nums));
+        }
+    }
+}
+
+impl fmt::Display for Elimination {
+    /// Formats the elimination as `r<row>c<col><><num>`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}<>{}", self.coord, self.num.to_char())
+    }
+}
+
+/// How the cells of a step fit together, for techniques where that is more
+/// than a set of cells.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Detail {
+    None,
+    /// The house in which a hidden single is the only place for its number.
+    House([Coord; 9]),
+    /// The pincers both see the pivot. An XY- or XYZ-Wing pivot is a single
+    /// cell, and a W-Wing pivot is the only two places for a number in a
+    /// house, one seeing each pincer.
+    Wing {
+        pivot: Vec<Coord>,
+        pincers: [Coord; 2],
+    },
+    /// The two colors of a chain of conjugate pairs. Exactly one color holds
+    /// the number.
+    Coloring {
+        colors: [Vec<Coord>; 2],
+    },
+    /// An alternating inference chain. Its first or last number is true.
+    Chain(Vec<ChainNode>),
+    /// One chain for each way of filling a cell or placing a number in a
+    /// house, each starting from that choice and ending with the eliminated
+    /// number turned off.
+    Forcing(Vec<Vec<ChainNode>>),
+    /// Two almost locked sets joined by a restricted common number, which
+    /// can go in at most one of them.
+    AlsXz {
+        sets: [Als; 2],
+        restricted: Number,
+    },
+    /// A stem cell with a petal for each of its possible numbers: an almost
+    /// locked set in which every place for that number sees the stem.
+    DeathBlossom {
+        stem: Coord,
+        petals: Vec<(Number, Als)>,
+    },
+}
+
+/// An almost locked set: empty cells of one house with one more possible
+/// number between them than there are cells.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Als {
+    pub cells: Vec<Coord>,
+    pub nums: PossibleNums,
+}
+impl Als {
+    /// The cells of the set that `num` can go in.
+    fn places(&self, grid: &Grid, num: Number) -> Vec<Coord> {
+        places(grid, &self.cells, num)
+    }
+}
+
+impl fmt::Display for Als {
+    /// Formats the set like `r1c1, r1c2 (1/2/3)`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let cells: Vec<String> = self.cells.iter().map(Coord::to_string).collect();
+        write!(f, "{} ({})", cells.join(", "), format_nums(self.nums))
+    }
+}
+
+/// Names a house like `row 1`, `column 1` or `box 1`.
+fn house_name(house: &[Coord; 9]) -> String {
+    let (first, last) = (house[0], house[8]);
+    if first.parent_row() == last.parent_row() {
+        format!("row {}", first.parent_row() + 1)
+    } else if first.parent_col() == last.parent_col() {
+        format!("column {}", first.parent_col() + 1)
+    } else {
+        format!("box {}", first.parent_box() + 1)
+    }
+}
+
+/// Formats numbers like `1/5`.
+fn format_nums(nums: PossibleNums) -> String {
+    let nums: Vec<String> = nums.iter().map(|num| num.to_char().to_string()).collect();
+    nums.join("/")
+}
+
+/// How a number in a chain relates to the next one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Link {
+    /// If this number is false, the next one is true.
+    Strong,
+    /// If this number is true, the next one is false.
+    Weak,
+}
+
+/// A number in a cell, as part of a chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChainNode {
+    pub coord: Coord,
+    pub num: Number,
+    /// The link to the next node, or `None` for the last one.
+    pub link: Option<Link>,
+}
+
+/// Formats a chain like `(5)r1c1=(5)r1c5-(5)r3c5`, where `=` is a strong
+/// link and `-` a weak one.
+fn format_chain(chain: &[ChainNode]) -> String {
+    let mut formatted = String::new();
+    for node in chain {
+        formatted.push_str(&format!("({}){}", node.num.to_char(), node.coord));
+        match node.link {
+            Some(Link::Strong) => formatted.push('='),
+            Some(Link::Weak) => formatted.push('-'),
+            None => {}
+        }
+    }
+    formatted
+}
+
+/// Settings for the techniques the logical solver uses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Options {
+    /// The most numbers a chain may have.
+    pub max_chain_length: usize,
+    /// Use techniques that are only valid for puzzles with exactly one
+    /// solution.
+    pub assume_unique: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            max_chain_length: 12,
+            assume_unique: false,
+        }
+    }
+}
+
+/// One application of a technique: the cells and numbers that make up the
+/// pattern, and the eliminations it allows.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Step {
+    pub technique: Technique,
+    pub cells: Vec<Coord>,
+    pub nums: PossibleNums,
+    pub placements: Vec<Placement>,
+    pub eliminations: Vec<Elimination>,
+    pub detail: Detail,
+}
+impl Step {
+    /// Fill in the placed numbers, removing each from the possible numbers
+    /// of the cells it sees, and remove the eliminated numbers.
+    pub fn apply(&self, grid: &mut Grid) {
+        for placement in &self.placements {
+            grid.set(placement.coord, Cell::Fixed(placement.num));
+            let seen: Vec<Coord> = candidate_cells(grid)
+                .filter(|(coord, possible_nums)| {
+                    possible_nums.contains(placement.num) && coord.sees(&placement.coord)
+                })
+                .map(|(coord, _)| coord)
+                .collect();
+            for coord in seen {
+                Elimination {
+                    coord,
+                    num: placement.num,
+                }
+                .apply(grid);
+            }
+        }
+        for elimination in &self.eliminations {
+            elimination.apply(grid);
+        }
+    }
+}
+
+impl fmt::Display for Step {
+    /// Formats the step like `Naked Pair 1/5 in r1c2, r1c7: r1c3<>1`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let nums = format_nums(self.nums);
+        let cells: Vec<String> = self.cells.iter().map(Coord::to_string).collect();
+        let changes: Vec<String> = self
+            .placements
+            .iter()
+            .map(Placement::to_string)
+            .chain(self.eliminations.iter().map(Elimination::to_string))
+            .collect();
+
+        match &self.detail {
+            Detail::None => write!(
+                f,
+                "{} {} in {}: {}",
+                self.technique,
+                nums,
+                cells.join(", "),
+                changes.join(", ")
+            ),
+            Detail::House(house) => write!(
+                f,
+                "{} {} in {}: {}",
+                self.technique,
+                nums,
+                house_name(house),
+                changes.join(", ")
+            ),
+            Detail::Wing { pivot, pincers } => {
+                let pivot: Vec<String> = pivot.iter().map(Coord::to_string).collect();
+                write!(
+                    f,
+                    "{} {} with pivot {} and pincers {}, {}: {}",
+                    self.technique,
+                    nums,
+                    pivot.join("="),
+                    pincers[0],
+                    pincers[1],
+                    changes.join(", ")
+                )
+            }
+            Detail::Coloring { colors } => {
+                let [a, b] = colors.each_ref().map(|color| {
+                    color
+                        .iter()
+                        .map(Coord::to_string)
+                        .collect::<Vec<String>>()
+                        .join(", ")
+                });
+                write!(
+                    f,
+                    "{} {} with colors {} and {}: {}",
+                    self.technique,
+                    nums,
+                    a,
+                    b,
+                    changes.join(", ")
+                )
+            }
+            Detail::Chain(chain) => write!(
+                f,
+                "{} {}: {}",
+                self.technique,
+                format_chain(chain),
+                changes.join(", ")
+            ),
+            Detail::Forcing(chains) => {
+                let chains: Vec<String> = chains.iter().map(|chain| format_chain(chain)).collect();
+                write!(
+                    f,
+                    "{} {}: {}",
+                    self.technique,
+                    chains.join("; "),
+                    changes.join(", ")
+                )
+            }
+            Detail::AlsXz { sets, restricted } => write!(
+                f,
+                "{} {} and {} with restricted common {}: {}",
+                self.technique,
+                sets[0],
+                sets[1],
+                restricted.to_char(),
+                changes.join(", ")
+            ),
+            Detail::DeathBlossom { stem, petals } => {
+                let petals: Vec<String> = petals
+                    .iter()
+                    .map(|(num, als)| format!("{} {}", num.to_char(), als))
+                    .collect();
+                write!(
+                    f,
+                    "{} with stem {} and petals {}: {}",
+                    self.technique,
+                    stem,
+                    petals.join("; "),
+                    changes.join(", ")
+                )
+            }
+        }
+    }
+}
+
+/// How hard a puzzle is to solve without guessing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Grade {
+    /// Naked and hidden singles are enough.
+    Singles,
+    /// The hardest technique needed.
+    Technique(Technique),
+    /// The techniques run out before the puzzle is solved.
+    RequiresGuessing,
+}
+
+impl fmt::Display for Grade {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Grade::Singles => write!(f, "Singles"),
+            Grade::Technique(technique) => write!(f, "{}", technique),
+            Grade::RequiresGuessing => write!(f, "Requires guessing"),
+        }
+    }
+}
+
+/// A puzzle worked through step by step, always using the easiest
+/// technique that makes progress.
+#[derive(Debug, Clone)]
+pub struct Explanation {
+    /// The steps taken, in order.
+    pub steps: Vec<Step>,
+    /// The grid after the last step, solved unless guessing is required.
+    pub grid: Grid,
+    /// The hardest technique used.
+    pub grade: Grade,
+}
+
+/// Work through a puzzle the way a person would, starting from the
+/// possible numbers left by its clues. Stops when the puzzle is solved or
+/// no technique makes progress, in which case it requires guessing.
+pub fn explain(puzzle: &Grid, options: &Options) -> Explanation {
+    let mut grid = *puzzle;
+    mark_candidates(&mut grid);
+
+    let mut steps = Vec::new();
+    let mut grade = Grade::Singles;
+    while let Some(step) = next_step(&grid, options) {
+        if !matches!(
+            step.technique,
+            Technique::NakedSingle | Technique::HiddenSingle
+        ) {
+            grade = grade.max(Grade::Technique(step.technique));
+        }
+        step.apply(&mut grid);
+        steps.push(step);
+    }
+
+    if grid.clue_count() < 81 || !grid.is_legal() {
+        grade = Grade::RequiresGuessing;
+    }
+    Explanation { steps, grid, grade }
+}
+
+/// Remove the numbers fixed in the row, column and box of each empty cell
+/// from its possible numbers, without filling in any cells.
+fn mark_candidates(grid: &mut Grid) {
+    let cells: Vec<(Coord, PossibleNums)> = candidate_cells(grid).collect();
+    for (coord, mut possible_nums) in cells {
+        let houses = [
+            row_coords(coord.parent_row()),
+            col_coords(coord.parent_col()),
+            box_coords(coord.parent_box()),
+        ];
+        for other in houses.iter().flatten() {
+            if let Cell::Fixed(num) = grid.get(*other) {
+                possible_nums.remove(num);
+            }
+        }
+        grid.set(coord, Cell::Empty(possible_nums));
+    }
+}
+
+/// The positions of every row, column and box.
+fn houses() -> impl Iterator<Item = [Coord; 9]> {
+    (0..9).flat_map(|i| [row_coords(i), col_coords(i), box_coords(i)])
+}
+
+/// Finders for every technique, each only finding techniques harder than
+/// those of the finders before it.
+const FINDERS: [fn(&Grid, &Options) -> Vec<Step>; 10] = [
+    |grid, _| singles(grid),
+    |grid, _| intersections(grid),
+    |grid, _| subsets(grid),
+    |grid, _| fish(grid),
+    |grid, _| wings(grid),
+    |grid, options| {
+        if options.assume_unique {
+            uniqueness(grid)
+        } else {
+            Vec::new()
+        }
+    },
+    |grid, _| coloring(grid),
+    |grid, options| chains(grid, options.max_chain_length),
+    |grid, _| als(grid),
+    |grid, options| forcing_chains(grid, options.max_chain_length),
+];
+
+/// A step using the easiest technique that places a number or eliminates a
+/// possible number.
+pub fn next_step(grid: &Grid, options: &Options) -> Option<Step> {
+    FINDERS
+        .iter()
+        .map(|find| find(grid, options))
+        .find(|steps| !steps.is_empty())?
+        .into_iter()
+        .min_by_key(|step| step.technique)
+}
+
+/// Every naked and hidden single in the grid: a cell with one possible
+/// number left, or a number with one place left in a house. A cell is only
+/// placed once, as a naked single if it is both.
+pub fn singles(grid: &Grid) -> Vec<Step> {
+    let mut steps: Vec<Step> = candidate_cells(grid)
+        .filter_map(|(coord, possible_nums)| {
+            let num = possible_nums.single()?;
+            Some(single(Technique::NakedSingle, coord, num, Detail::None))
+        })
+        .collect();
+
+    for house in houses() {
+        for num in Number::ARRAY_ALL {
+            let [coord] = places(grid, &house, num)[..] else {
+                continue;
+            };
+            if !steps.iter().any(|step| step.cells == [coord]) {
+                steps.push(single(
+                    Technique::HiddenSingle,
+                    coord,
+                    num,
+                    Detail::House(house),
+                ));
+            }
+        }
+    }
+
+    steps
+}
+
+/// A step placing `num` in the cell at `coord`.
+fn single(technique: Technique, coord: Coord, num: Number, detail: Detail) -> Step {
+    let mut nums = PossibleNums::NONE;
+    nums.insert(num);
+    Step {
+        technique,
+        cells: vec![coord],
+        nums,
+        placements: vec![Placement { coord, num }],
+        eliminations: Vec::new(),
+        detail,
+    }
+}
+
+/// Every naked and hidden subset of size 2 to 4 in the grid that eliminates
+/// at least one possible number.
+pub fn subsets(grid: &Grid) -> Vec<Step> {
+    houses()
+        .flat_map(|coords| house_subsets(&coords.map(|coord| grid.get(coord)), &coords))
+        .collect()
+}
+
+/// Every naked and hidden subset of size 2 to 4 in a house that eliminates
+/// at least one possible number, smallest first.
+pub(crate) fn house_subsets(house: &[Cell; 9], coords: &[Coord; 9]) -> Vec<Step> {
+    let mut steps = Vec::new();
+    for size in 2..=4 {
+        steps.extend(naked_subsets(house, coords, size));
+        steps.extend(hidden_subsets(house, coords, size));
+    }
+
+    steps
+}
+
+/// Every locked candidate in the grid that eliminates at least one possible
+/// number.
+///
+/// When a number's places in a box all lie in one row or column, it must go
+/// in that line inside the box, so it is removed from the rest of the line
+/// (pointing). When its places in a row or column all lie in one box, it is
+/// removed from the rest of the box (box/line reduction).
+pub fn intersections(grid: &Grid) -> Vec<Step> {
+    let mut steps = Vec::new();
+    for box_ in 0..9 {
+        let box_ = box_coords(box_);
+        let first = box_[0];
+        let lines = (0..3)
+            .map(|i| row_coords(first.parent_row() + i))
+            .chain((0..3).map(|i| col_coords(first.parent_col() + i)));
+
+        for line in lines {
+            for num in Number::ARRAY_ALL {
+                steps.extend(locked_candidate(
+                    grid,
+                    num,
+                    &box_,
+                    &line,
+                    Technique::Pointing,
+                ));
+                steps.extend(locked_candidate(
+                    grid,
+                    num,
+                    &line,
+                    &box_,
+                    Technique::BoxLineReduction,
+                ));
+            }
+        }
+    }
+
+    steps
+}
+
+/// If every place for `num` in `from` is also in `to`, remove it from the
+/// rest of `to`.
+fn locked_candidate(
+    grid: &Grid,
+    num: Number,
+    from: &[Coord; 9],
+    to: &[Coord; 9],
+    technique: Technique,
+) -> Option<Step> {
+    // A single place is a hidden single, not a locked candidate.
+    let cells = places(grid, from, num);
+    if cells.len() < 2 || cells.iter().any(|coord| !to.contains(coord)) {
+        return None;
+    }
+
+    let eliminations: Vec<Elimination> = places(grid, to, num)
+        .into_iter()
+        .filter(|coord| !from.contains(coord))
+        .map(|coord| Elimination { coord, num })
+        .collect();
+    if eliminations.is_empty() {
+        return None;
+    }
+
+    let mut nums = PossibleNums::NONE;
+    nums.insert(num);
+    Some(Step {
+        technique,
+        cells,
+        nums,
+        placements: Vec::new(),
+        eliminations,
+        detail: Detail::None,
+    })
+}
+
+/// The empty cells among `coords` that `num` can go in, or none if `num` is
+/// already fixed in one of them.
+///
+/// Propagation can fix a cell before removing its number from the other
+/// cells of a house, so the remaining places are not the only ones left.
+fn places(grid: &Grid, coords: &[Coord], num: Number) -> Vec<Coord> {
+    if coords
+        .iter()
+        .any(|coord| grid.get(*coord) == Cell::Fixed(num))
+    {
+        return Vec::new();
+    }
+
+    coords
+        .iter()
+        .copied()
+        .filter(|coord| is_candidate(grid, *coord, num))
+        .collect()
+}
+
+/// Whether the cell is empty and `num` can go in it.
+fn is_candidate(grid: &Grid, coord: Coord, num: Number) -> bool {
+    matches!(grid.get(coord), Cell::Empty(possible_nums) if possible_nums.contains(num))
+}
+
+/// `size` empty cells that only have `size` possible numbers between them.
+/// Those numbers must go in those cells, so they are removed from the rest
+/// of the house.
+fn naked_subsets(house: &[Cell; 9], coords: &[Coord; 9], size: usize) -> Vec<Step> {
+    let candidates: Vec<(usize, PossibleNums)> = house
+        .iter()
+        .enumerate()
+        .filter_map(|(index, cell)| match cell {
+            Cell::Empty(possible_nums) if (2..=size as u32).contains(&possible_nums.len()) => {
+                Some((index, *possible_nums))
+            }
+            _ => None,
+        })
+        .collect();
+
+    let mut steps = Vec::new();
+    for subset in combinations(&candidates, size) {
+        let nums = subset
+            .iter()
+            .fold(PossibleNums::NONE, |nums, (_, possible_nums)| {
+                nums.union(*possible_nums)
+            });
+        if nums.len() as usize != size {
+            continue;
+        }
+
+        let mut eliminations = Vec::new();
+        for (index, cell) in house.iter().enumerate() {
+            if subset.iter().any(|(member, _)| *member == index) {
+                continue;
+            }
+            if let Cell::Empty(possible_nums) = cell {
+                for num in possible_nums.intersection(nums) {
+                    eliminations.push(Elimination {
+                        coord: coords[index],
+                        num,
+                    });
+                }
+            }
+        }
+
+        if !eliminations.is_empty() {
+            steps.push(Step {
+                technique: Technique::naked(size),
+                cells: subset.iter().map(|(index, _)| coords[*index]).collect(),
+                nums,
+                placements: Vec::new(),
+                eliminations,
+                detail: Detail::None,
+            });
+        }
+    }
+
+    steps
+}
+
+/// `size` numbers that can only go in the same `size` empty cells. Those
+/// cells must hold those numbers, so every other number is removed from
+/// them.
+fn hidden_subsets(house: &[Cell; 9], coords: &[Coord; 9], size: usize) -> Vec<Step> {
+    // The empty cells each number can go in, as a bit per house index.
+    let candidates: Vec<(Number, u16)> = Number::ARRAY_ALL
+        .into_iter()
+        .filter(|num| !house.contains(&Cell::Fixed(*num)))
+        .filter_map(|num| {
+            let places = house
+                .iter()
+                .enumerate()
+                .filter(|(_, cell)| matches!(cell, Cell::Empty(possible_nums) if possible_nums.contains(num)))
+                .fold(0u16, |places, (index, _)| places | 1 << index);
+
+            (2..=size as u32)
+                .contains(&places.count_ones())
+                .then_some((num, places))
+        })
+        .collect();
+
+    let mut steps = Vec::new();
+    for subset in combinations(&candidates, size) {
+        let places = subset.iter().fold(0u16, |all, (_, places)| all | places);
+        if places.count_ones() as usize != size {
+            continue;
+        }
+
+        let mut nums = PossibleNums::NONE;
+        for (num, _) in &subset {
+            nums.insert(*num);
+        }
+
+        let mut cells = Vec::new();
+        let mut eliminations = Vec::new();
+        for (index, cell) in house.iter().enumerate() {
+            if places & 1 << index == 0 {
+                continue;
+            }
+            cells.push(coords[index]);
+            if let Cell::Empty(possible_nums) = cell {
+                for num in possible_nums.iter().filter(|num| !nums.contains(*num)) {
+                    eliminations.push(Elimination {
+                        coord: coords[index],
+                        num,
+                    });
+                }
+            }
+        }
+
+        if !eliminations.is_empty() {
+            steps.push(Step {
+                technique: Technique::hidden(size),
+                cells,
+                nums,
+                placements: Vec::new(),
+                eliminations,
+                detail: Detail::None,
+            });
+        }
+    }
+
+    steps
+}
+
+/// Every X-Wing, Swordfish and Jellyfish in the grid that eliminates at
+/// least one possible number, smallest first.
+///
+/// When a number's places in `size` rows lie in only `size` columns, each
+/// of those columns gets the number in one of the rows, so it is removed
+/// from the rest of the columns. The same holds with rows and columns
+/// swapped.
+pub fn fish(grid: &Grid) -> Vec<Step> {
+    let mut steps = Vec::new();
+    for size in 2..=4 {
+        for num in Number::ARRAY_ALL {
+            steps.extend(line_fish(grid, num, size, row_coords, col_coords));
+            steps.extend(line_fish(grid, num, size, col_coords, row_coords));
+        }
+    }
+
+    steps
+}
+
+/// Fish for `num` with `base` lines as the defining set and `cover` lines
+/// as the lines eliminations are made from.
+fn line_fish(
+    grid: &Grid,
+    num: Number,
+    size: usize,
+    base: fn(u8) -> [Coord; 9],
+    cover: fn(u8) -> [Coord; 9],
+) -> Vec<Step> {
+    // The cover lines `num` can go in for each base line, as a bit per
+    // cover line.
+    let candidates: Vec<(u8, u16)> = (0..9)
+        .filter_map(|line| {
+            let coords = base(line);
+            let covers = places(grid, &coords, num)
+                .into_iter()
+                .filter_map(|coord| coords.iter().position(|other| *other == coord))
+                .fold(0u16, |covers, index| covers | 1 << index);
+
+            (2..=size as u32)
+                .contains(&covers.count_ones())
+                .then_some((line, covers))
+        })
+        .collect();
+
+    let mut nums = PossibleNums::NONE;
+    nums.insert(num);
+
+    let mut steps = Vec::new();
+    for subset in combinations(&candidates, size) {
+        let covers = subset.iter().fold(0u16, |all, (_, covers)| all | covers);
+        if covers.count_ones() as usize != size {
+            continue;
+        }
+
+        let mut cells = Vec::new();
+        let mut eliminations = Vec::new();
+        for cover_line in (0..9).filter(|line| covers & 1 << line != 0) {
+            for (index, coord) in cover(cover_line).into_iter().enumerate() {
+                if !is_candidate(grid, coord, num) {
+                    continue;
+                }
+                // The position of a cell in a cover line is its base line.
+                if subset.iter().any(|(line, _)| *line as usize == index) {
+                    cells.push(coord);
+                } else {
+                    eliminations.push(Elimination { coord, num });
+                }
+            }
+        }
+
+        if !eliminations.is_empty() {
+            cells.sort_by_key(|coord| (coord.parent_row(), coord.parent_col()));
+            steps.push(Step {
+                technique: Technique::fish(size),
+                cells,
+                nums,
+                placements: Vec::new(),
+                eliminations,
+                detail: Detail::None,
+            });
+        }
+    }
+
+    steps
+}
+
+/// Every XY-Wing, XYZ-Wing and W-Wing in the grid that eliminates at least
+/// one possible number.
+pub fn wings(grid: &Grid) -> Vec<Step> {
+    let mut steps = xy_wings(grid);
+    steps.extend(w_wings(grid));
+    steps
+}
+
+/// A pivot with the possible numbers `x`/`y` (or `x`/`y`/`z` for an
+/// XYZ-Wing) that sees pincers with `x`/`z` and `y`/`z`. Whichever number
+/// the pivot takes, one pincer is `z`, so `z` is removed from every cell
+/// that sees both pincers (and the pivot, for an XYZ-Wing).
+fn xy_wings(grid: &Grid) -> Vec<Step> {
+    let bivalue: Vec<(Coord, PossibleNums)> = candidate_cells(grid)
+        .filter(|(_, possible_nums)| possible_nums.len() == 2)
+        .collect();
+
+    let mut steps = Vec::new();
+    for (pivot, pivot_nums) in candidate_cells(grid) {
+        // How many of the pivot's numbers each pincer has.
+        let (technique, shared) = match pivot_nums.len() {
+            2 => (Technique::XyWing, 1),
+            3 => (Technique::XyzWing, 2),
+            _ => continue,
+        };
+        let pincers: Vec<(Coord, PossibleNums)> = bivalue
+            .iter()
+            .copied()
+            .filter(|(coord, possible_nums)| {
+                pivot.sees(coord) && possible_nums.intersection(pivot_nums).len() == shared
+            })
+            .collect();
+
+        for pair in combinations(&pincers, 2) {
+            let [(a, a_nums), (b, b_nums)] = [pair[0], pair[1]];
+            let nums = pivot_nums.union(a_nums).union(b_nums);
+            if nums.len() != 3 || a_nums == b_nums {
+                continue;
+            }
+            let Some(z) = a_nums.intersection(b_nums).single() else {
+                continue;
+            };
+
+            let eliminations: Vec<Elimination> = candidate_cells(grid)
+                .filter(|(coord, possible_nums)| {
+                    possible_nums.contains(z)
+                        && ![pivot, a, b].contains(coord)
+                        && coord.sees(&a)
+                        && coord.sees(&b)
+                        && (technique == Technique::XyWing || coord.sees(&pivot))
+                })
+                .map(|(coord, _)| Elimination { coord, num: z })
+                .collect();
+
+            if !eliminations.is_empty() {
+                steps.push(Step {
+                    technique,
+                    cells: vec![pivot, a, b],
+                    nums,
+                    placements: Vec::new(),
+                    eliminations,
+                    detail: Detail::Wing {
+                        pivot: vec![pivot],
+                        pincers: [a, b],
+                    },
+                });
+            }
+        }
+    }
+
+    steps
+}
+
+/// Two cells with the same possible numbers `x`/`y` that don't see each
+/// other, joined through any row, column or box where `x` isn't fixed and
+/// has exactly two places left, one seeing each cell. `x` must go in one of
+/// those places, which rules it out of the cell that place sees, so one of
+/// the cells must be `y` and `y` is removed from every cell that sees both.
+fn w_wings(grid: &Grid) -> Vec<Step> {
+    let bivalue: Vec<(Coord, PossibleNums)> = candidate_cells(grid)
+        .filter(|(_, possible_nums)| possible_nums.len() == 2)
+        .collect();
+
+    let mut steps = Vec::new();
+    for pair in combinations(&bivalue, 2) {
+        let [(a, a_nums), (b, b_nums)] = [pair[0], pair[1]];
+        if a_nums != b_nums || a.sees(&b) {
+            continue;
+        }
+
+        for x in a_nums {
+            let Some(y) = a_nums.iter().find(|num| *num != x) else {
+                continue;
+            };
+            let eliminations: Vec<Elimination> = candidate_cells(grid)
+                .filter(|(coord, possible_nums)| {
+                    possible_nums.contains(y) && coord.sees(&a) && coord.sees(&b)
+                })
+                .map(|(coord, _)| Elimination { coord, num: y })
+                .collect();
+            if eliminations.is_empty() {
+                continue;
+            }
+
+            for house in houses() {
+                let [c, d] = match places(grid, &house, x)[..] {
+                    [c, d] => [c, d],
+                    _ => continue,
+                };
+                // A cell doesn't see itself and `a` doesn't see `b`, so
+                // neither place can be `a` or `b`.
+                let link = if c.sees(&a) && d.sees(&b) {
+                    [c, d]
+                } else if d.sees(&a) && c.sees(&b) {
+                    [d, c]
+                } else {
+                    continue;
+                };
+
+                let mut nums = PossibleNums::NONE;
+                nums.insert(x);
+                nums.insert(y);
+                steps.push(Step {
+                    technique: Technique::WWing,
+                    cells: vec![a, link[0], link[1], b],
+                    nums,
+                    placements: Vec::new(),
+                    eliminations,
+                    detail: Detail::Wing {
+                        pivot: link.to_vec(),
+                        pincers: [a, b],
+                    },
+                });
+                break;
+            }
+        }
+    }
+
+    steps
+}
+
+/// Every Unique Rectangle and BUG+1 in the grid.
+///
+/// These rely on the puzzle having a single solution, since they remove
+/// numbers that would otherwise leave it with two.
+pub fn uniqueness(grid: &Grid) -> Vec<Step> {
+    let mut steps = unique_rectangles(grid);
+    steps.extend(bug_plus_one(grid));
+    steps
+}
+
+/// Four empty cells at the corners of a rectangle spanning two rows, two
+/// columns and two boxes, that all have the possible numbers `a` and `b`.
+/// If they could only hold `a` and `b`, they could be swapped for a second
+/// solution, so some of them must hold another number.
+fn unique_rectangles(grid: &Grid) -> Vec<Step> {
+    let mut steps = Vec::new();
+    for (r1, r2) in (0..9).flat_map(|r1| (r1 + 1..9).map(move |r2| (r1, r2))) {
+        for (c1, c2) in (0..9).flat_map(|c1| (c1 + 1..9).map(move |c2| (c1, c2))) {
+            // Exactly two boxes: the rows share a band or the columns a
+            // stack, but not both.
+            if (r1 / 3 == r2 / 3) == (c1 / 3 == c2 / 3) {
+                continue;
+            }
+
+            let corners = [(r1, c1), (r1, c2), (r2, c1), (r2, c2)]
+                .map(|(row, col)| Coord::from_row_col(row, col));
+            // A fixed corner has no possible numbers in common with the rest.
+            let possible = corners.map(|corner| match grid.get(corner) {
+                Cell::Empty(possible_nums) => possible_nums,
+                Cell::Fixed(_) => PossibleNums::NONE,
+            });
+            let common = possible
+                .iter()
+                .fold(PossibleNums::ALL, |common, possible_nums| {
+                    common.intersection(*possible_nums)
+                });
+
+            for pair in combinations(&common.iter().collect::<Vec<Number>>(), 2) {
+                let mut ab = PossibleNums::NONE;
+                ab.insert(pair[0]);
+                ab.insert(pair[1]);
+                steps.extend(rectangle(grid, &corners, &possible, ab));
+            }
+        }
+    }
+
+    steps
+}
+
+/// The Unique Rectangle steps for the corners of a rectangle, whose
+/// possible numbers all include the pair `ab`.
+fn rectangle(
+    grid: &Grid,
+    corners: &[Coord; 4],
+    possible: &[PossibleNums; 4],
+    ab: PossibleNums,
+) -> Vec<Step> {
+    let step = |technique, eliminations: Vec<Elimination>| Step {
+        technique,
+        cells: corners.to_vec(),
+        nums: ab,
+        placements: Vec::new(),
+        eliminations,
+        detail: Detail::None,
+    };
+    let floor: Vec<usize> = (0..4).filter(|i| possible[*i] == ab).collect();
+
+    // Type 1: the only corner with other numbers can't be `a` or `b`.
+    if let [a, b, c] = floor[..] {
+        let roof = 6 - a - b - c;
+        let eliminations = ab
+            .iter()
+            .map(|num| Elimination {
+                coord: corners[roof],
+                num,
+            })
+            .collect();
+        return vec![step(Technique::UniqueRectangle1, eliminations)];
+    }
+
+    // The other types have two floor corners in a row or column, leaving
+    // two roof corners that share it and have other numbers.
+    let [a, b] = match floor[..] {
+        [0, 1] => [2, 3],
+        [2, 3] => [0, 1],
+        [0, 2] => [1, 3],
+        [1, 3] => [0, 2],
+        _ => return Vec::new(),
+    };
+    let roof = [corners[a], corners[b]];
+    let roof_nums = [possible[a], possible[b]];
+    let mut extra = roof_nums[0].union(roof_nums[1]);
+    for num in ab {
+        extra.remove(num);
+    }
+    let roof_houses: Vec<[Coord; 9]> = houses()
+        .filter(|house| roof.iter().all(|coord| house.contains(coord)))
+        .collect();
+
+    let mut steps = Vec::new();
+
+    // Type 2: both roof corners have one other number, the same one, so it
+    // goes in one of them.
+    if let (Some(z), true) = (extra.single(), roof_nums[0] == roof_nums[1]) {
+        let eliminations: Vec<Elimination> = candidate_cells(grid)
+            .filter(|(coord, possible_nums)| {
+                possible_nums.contains(z) && roof.iter().all(|corner| coord.sees(corner))
+            })
+            .map(|(coord, _)| Elimination { coord, num: z })
+            .collect();
+        if !eliminations.is_empty() {
+            steps.push(step(Technique::UniqueRectangle2, eliminations));
+        }
+    }
+
+    // Type 3: one roof corner holds one of the other numbers, so together
+    // they act as a single cell with those numbers, which can form a naked
+    // subset with other cells of a house they share.
+    for house in &roof_houses {
+        let others: Vec<(Coord, PossibleNums)> = house
+            .iter()
+            .filter(|coord| !roof.contains(coord))
+            .filter_map(|coord| match grid.get(*coord) {
+                Cell::Empty(possible_nums) => Some((*coord, possible_nums)),
+                Cell::Fixed(_) => None,
+            })
+            .collect();
+
+        for size in 1..=3 {
+            for subset in combinations(&others, size) {
+                let nums = subset
+                    .iter()
+                    .fold(extra, |nums, (_, possible_nums)| nums.union(*possible_nums));
+                if nums.len() as usize != size + 1 {
+                    continue;
+                }
+
+                let eliminations: Vec<Elimination> = others
+                    .iter()
+                    .filter(|(coord, _)| subset.iter().all(|(member, _)| member != coord))
+                    .flat_map(|(coord, possible_nums)| {
+                        possible_nums
+                            .intersection(nums)
+                            .iter()
+                            .map(|num| Elimination { coord: *coord, num })
+                            .collect::<Vec<Elimination>>()
+                    })
+                    .collect();
+                if !eliminations.is_empty() {
+                    let mut found = step(Technique::UniqueRectangle3, eliminations);
+                    found.cells.extend(subset.iter().map(|(coord, _)| *coord));
+                    steps.push(found);
+                }
+            }
+        }
+    }
+
+    // Type 4: if one of `a` and `b` can only go in the roof corners of a
+    // house they share, the other one can go in neither.
+    for house in &roof_houses {
+        for num in ab {
+            if places(grid, house, num) != roof {
+                continue;
+            }
+            let eliminations = ab
+                .iter()
+                .filter(|other| *other != num)
+                .flat_map(|other| roof.map(|coord| Elimination { coord, num: other }))
+                .collect();
+            steps.push(step(Technique::UniqueRectangle4, eliminations));
+        }
+    }
+
+    steps
+}
+
+/// If every empty cell has two possible numbers, each appearing twice in
+/// each house, except for one cell with three, the puzzle would have two
+/// solutions without the number that appears three times in that cell's
+/// houses. That number is fixed by removing the other two.
+fn bug_plus_one(grid: &Grid) -> Vec<Step> {
+    let mut extra = candidate_cells(grid).filter(|(_, possible_nums)| possible_nums.len() != 2);
+    let (Some((coord, possible_nums)), None) = (extra.next(), extra.next()) else {
+        return Vec::new();
+    };
+    if possible_nums.len() != 3 {
+        return Vec::new();
+    }
+
+    let mut bug_num = None;
+    for house in houses() {
+        for num in Number::ARRAY_ALL {
+            match places(grid, &house, num).len() {
+                0 | 2 => {}
+                3 if house.contains(&coord)
+                    && possible_nums.contains(num)
+                    && bug_num.is_none_or(|bug_num| bug_num == num) =>
+                {
+                    bug_num = Some(num);
+                }
+                _ => return Vec::new(),
+            }
+        }
+    }
+    let Some(bug_num) = bug_num else {
+        return Vec::new();
+    };
+
+    vec![Step {
+        technique: Technique::BugPlusOne,
+        cells: vec![coord],
+        nums: possible_nums,
+        placements: Vec::new(),
+        eliminations: possible_nums
+            .iter()
+            .filter(|num| *num != bug_num)
+            .map(|num| Elimination { coord, num })
+            .collect(),
+        detail: Detail::None,
+    }]
+}
+
+/// Every color trap and color wrap in the grid.
+///
+/// Conjugate pairs, the two places for a number in a house that has only
+/// two, are chained together and colored alternately, so one color holds
+/// the number and the other doesn't. If two cells of the same color see
+/// each other, that color is wrong and the number is removed from all of
+/// its cells (wrap). Otherwise the number is removed from every cell that
+/// sees both colors (trap).
+pub fn coloring(grid: &Grid) -> Vec<Step> {
+    let mut steps = Vec::new();
+    for num in Number::ARRAY_ALL {
+        let mut links: Vec<[Coord; 2]> = Vec::new();
+        for house in houses() {
+            if let [a, b] = places(grid, &house, num)[..] {
+                if !links.contains(&[a, b]) {
+                    links.push([a, b]);
+                }
+            }
+        }
+
+        let mut nums = PossibleNums::NONE;
+        nums.insert(num);
+
+        let mut colored: Vec<Coord> = Vec::new();
+        for link in &links {
+            if colored.contains(&link[0]) {
+                continue;
+            }
+
+            // Color the chain containing this link, one color at a time.
+            let mut colors: [Vec<Coord>; 2] = [vec![link[0]], Vec::new()];
+            let mut pending = vec![(link[0], 0)];
+            while let Some((coord, color)) = pending.pop() {
+                for [a, b] in &links {
+                    let other = match coord {
+                        _ if *a == coord => *b,
+                        _ if *b == coord => *a,
+                        _ => continue,
+                    };
+                    if !colors[0].contains(&other) && !colors[1].contains(&other) {
+                        colors[1 - color].push(other);
+                        pending.push((other, 1 - color));
+                    }
+                }
+            }
+            colored.extend(colors.iter().flatten());
+            for color in &mut colors {
+                color.sort_by_key(|coord| (coord.parent_row(), coord.parent_col()));
+            }
+
+            let wrapped = colors.iter().position(|color| {
+                combinations(color, 2)
+                    .iter()
+                    .any(|pair| pair[0].sees(&pair[1]))
+            });
+            let (technique, eliminations) = match wrapped {
+                Some(color) => (
+                    Technique::ColorWrap,
+                    colors[color]
+                        .iter()
+                        .map(|coord| Elimination { coord: *coord, num })
+                        .collect(),
+                ),
+                None => (
+                    Technique::ColorTrap,
+                    candidate_cells(grid)
+                        .filter(|(coord, possible_nums)| {
+                            possible_nums.contains(num)
+                                && colors
+                                    .iter()
+                                    .all(|color| color.iter().any(|other| coord.sees(other)))
+                        })
+                        .map(|(coord, _)| Elimination { coord, num })
+                        .collect::<Vec<Elimination>>(),
+                ),
+            };
+
+            if !eliminations.is_empty() {
+                let mut cells: Vec<Coord> = colors.iter().flatten().copied().collect();
+                cells.sort_by_key(|coord| (coord.parent_row(), coord.parent_col()));
+                steps.push(Step {
+                    technique,
+                    cells,
+                    nums,
+                    placements: Vec::new(),
+                    eliminations,
+                    detail: Detail::Coloring { colors },
+                });
+            }
+        }
+    }
+
+    steps
+}
+
+/// Every possible number in the grid, with the strong and weak links
+/// between them.
+struct Links {
+    nodes: Vec<(Coord, Number)>,
+    strong: Vec<Vec<usize>>,
+    weak: Vec<Vec<usize>>,
+}
+impl Links {
+    fn new(grid: &Grid) -> Links {
+        let nodes: Vec<(Coord, Number)> = candidate_cells(grid)
+            .flat_map(|(coord, possible_nums)| possible_nums.iter().map(move |num| (coord, num)))
+            .collect();
+        let mut links = Links {
+            strong: vec![Vec::new(); nodes.len()],
+            weak: vec![Vec::new(); nodes.len()],
+            nodes,
+        };
+
+        // A cell with two possible numbers holds one of them, and a number
+        // with two places in a house goes in one of them.
+        let mut pairs = Vec::new();
+        for (coord, possible_nums) in candidate_cells(grid) {
+            if let [a, b] = possible_nums.iter().collect::<Vec<Number>>()[..] {
+                pairs.push([(coord, a), (coord, b)]);
+            }
+        }
+        for house in houses() {
+            for num in Number::ARRAY_ALL {
+                if let [a, b] = places(grid, &house, num)[..] {
+                    pairs.push([(a, num), (b, num)]);
+                }
+            }
+        }
+        for [a, b] in pairs {
+            let (a, b) = (links.index(a), links.index(b));
+            if !links.strong[a].contains(&b) {
+                links.strong[a].push(b);
+                links.strong[b].push(a);
+            }
+        }
+
+        let count = links.nodes.len();
+        for (a, b) in (0..count).flat_map(|a| (a + 1..count).map(move |b| (a, b))) {
+            if excludes(links.nodes[a], links.nodes[b]) {
+                links.weak[a].push(b);
+                links.weak[b].push(a);
+            }
+        }
+
+        links
+    }
+
+    /// The index of a possible number of the grid.
+    fn index(&self, node: (Coord, Number)) -> usize {
+        self.nodes
+            .iter()
+            .position(|other| *other == node)
+            .expect("only possible numbers are linked")
+    }
+
+    /// Search breadth first for what follows from `start` being true (`on`)
+    /// or false, through chains of at most `max_length` numbers.
+    fn implications(&self, start: usize, on: bool, max_length: usize) -> Implications {
+        // Each state is a node that is either false or true, at index
+        // `node * 2 + on`.
+        let state = |node: usize, on: bool| node * 2 + on as usize;
+        let mut previous = vec![None; self.nodes.len() * 2];
+        let mut length = vec![0; self.nodes.len() * 2];
+        previous[state(start, on)] = Some(state(start, on));
+        length[state(start, on)] = 1;
+
+        let mut queue = VecDeque::from([state(start, on)]);
+        while let Some(current) = queue.pop_front() {
+            if length[current] >= max_length {
+                continue;
+            }
+            let (node, on) = (current / 2, current % 2 == 1);
+            // A true number turns off its weak links, and a false one turns
+            // on its strong links.
+            let next = if on {
+                &self.weak[node]
+            } else {
+                &self.strong[node]
+            };
+            for &other in next {
+                let other = state(other, !on);
+                if previous[other].is_none() {
+                    previous[other] = Some(current);
+                    length[other] = length[current] + 1;
+                    queue.push_back(other);
+                }
+            }
+        }
+
+        Implications { previous }
+    }
+
+    /// The chain of numbers leading to a state found by `implications`.
+    fn chain(&self, implications: &Implications, node: usize, on: bool) -> Vec<ChainNode> {
+        let mut states = vec![node * 2 + on as usize];
+        while let Some(previous) = implications.previous[*states.last().unwrap()] {
+            if previous == *states.last().unwrap() {
+                break;
+            }
+            states.push(previous);
+        }
+        states.reverse();
+
+        states
+            .iter()
+            .enumerate()
+            .map(|(i, state)| {
+                let (coord, num) = self.nodes[state / 2];
+                let link = states.get(i + 1).map(|_| {
+                    if state % 2 == 0 {
+                        Link::Strong
+                    } else {
+                        Link::Weak
+                    }
+                });
+                ChainNode { coord, num, link }
+            })
+            .collect()
+    }
+}
+
+/// The states reached by `Links::implications`, each with the state it was
+/// reached from.
+struct Implications {
+    previous: Vec<Option<usize>>,
+}
+impl Implications {
+    fn reaches(&self, node: usize, on: bool) -> bool {
+        self.previous[node * 2 + on as usize].is_some()
+    }
+}
+
+/// Whether two different numbers can't both be true: they are in the same
+/// cell, or they are the same number in cells that see each other.
+fn excludes(a: (Coord, Number), b: (Coord, Number)) -> bool {
+    a != b && (a.0 == b.0 || (a.1 == b.1 && a.0.sees(&b.0)))
+}
+
+/// Every X-Chain, XY-Chain and AIC of at most `max_length` numbers that
+/// eliminates at least one possible number.
+///
+/// An alternating inference chain starts and ends with a strong link, so
+/// if its first number is false its last one is true. Either way, any
+/// number that excludes both ends is removed. X-Chains use a single number,
+/// and XY-Chains only use cells with two possible numbers for their strong
+/// links.
+pub fn chains(grid: &Grid, max_length: usize) -> Vec<Step> {
+    let links = Links::new(grid);
+    let key = |(coord, num): (Coord, Number)| (coord.parent_row(), coord.parent_col(), num.to_u8());
+
+    let mut steps = Vec::new();
+    for start in 0..links.nodes.len() {
+        let implications = links.implications(start, false, max_length);
+        for end in 0..links.nodes.len() {
+            if key(links.nodes[start]) >= key(links.nodes[end]) || !implications.reaches(end, true)
+            {
+                continue;
+            }
+
+            let chain = links.chain(&implications, end, true);
+            if chain.len() < 4
+                || chain.iter().enumerate().any(|(i, node)| {
+                    chain[..i]
+                        .iter()
+                        .any(|other| other.coord == node.coord && other.num == node.num)
+                })
+            {
+                continue;
+            }
+
+            let eliminations: Vec<Elimination> = links.weak[start]
+                .iter()
+                .filter(|node| **node != end && excludes(links.nodes[**node], links.nodes[end]))
+                .map(|node| {
+                    let (coord, num) = links.nodes[*node];
+                    Elimination { coord, num }
+                })
+                .collect();
+            if eliminations.is_empty() {
+                continue;
+            }
+
+            let technique = if chain.iter().all(|node| node.num == chain[0].num) {
+                Technique::XChain
+            } else if chain.windows(2).all(|pair| match pair[0].link {
+                Some(Link::Strong) => pair[0].coord == pair[1].coord,
+                _ => pair[0].num == pair[1].num,
+            }) {
+                Technique::XyChain
+            } else {
+                Technique::Aic
+            };
+
+            steps.push(chain_step(technique, &[chain], eliminations));
+        }
+    }
+
+    steps
+}
+
+/// Every cell and unit forcing chain of at most `max_length` numbers.
+///
+/// One of the possible numbers of a cell must be true, as must one of the
+/// places for a number in a house. A number that each of those choices
+/// turns off is removed.
+pub fn forcing_chains(grid: &Grid, max_length: usize) -> Vec<Step> {
+    let links = Links::new(grid);
+    let mut cache: Vec<Option<Implications>> = (0..links.nodes.len()).map(|_| None).collect();
+
+    let cells = candidate_cells(grid).map(|(coord, possible_nums)| {
+        let branches: Vec<usize> = possible_nums
+            .iter()
+            .map(|num| links.index((coord, num)))
+            .collect();
+        (Technique::CellForcingChain, branches)
+    });
+    let units: Vec<(Technique, Vec<usize>)> = houses()
+        .flat_map(|house| Number::ARRAY_ALL.map(|num| (house, num)))
+        .map(|(house, num)| {
+            let branches = places(grid, &house, num)
+                .into_iter()
+                .map(|coord| links.index((coord, num)))
+                .collect();
+            (Technique::UnitForcingChain, branches)
+        })
+        .collect();
+
+    let mut steps = Vec::new();
+    for (technique, branches) in cells.collect::<Vec<_>>().into_iter().chain(units) {
+        if branches.len() < 2 {
+            continue;
+        }
+        for branch in &branches {
+            if cache[*branch].is_none() {
+                cache[*branch] = Some(links.implications(*branch, true, max_length));
+            }
+        }
+        let implications: Vec<&Implications> = branches
+            .iter()
+            .map(|branch| cache[*branch].as_ref().unwrap())
+            .collect();
+
+        for (node, &(coord, num)) in links.nodes.iter().enumerate() {
+            if !implications.iter().all(|found| found.reaches(node, false)) {
+                continue;
+            }
+            let chains: Vec<Vec<ChainNode>> = implications
+                .iter()
+                .map(|found| links.chain(found, node, false))
+                .collect();
+            steps.push(chain_step(
+                technique,
+                &chains,
+                vec![Elimination { coord, num }],
+            ));
+        }
+    }
+
+    steps
+}
+
+/// A step for the chains of a chaining technique.
+fn chain_step(
+    technique: Technique,
+    chains: &[Vec<ChainNode>],
+    eliminations: Vec<Elimination>,
+) -> Step {
+    let mut cells = Vec::new();
+    let mut nums = PossibleNums::NONE;
+    for node in chains.iter().flatten() {
+        if !cells.contains(&node.coord) {
+            cells.push(node.coord);
+        }
+        nums.insert(node.num);
+    }
+
+    let detail = match chains {
+        [chain] => Detail::Chain(chain.clone()),
+        _ => Detail::Forcing(chains.to_vec()),
+    };
+    Step {
+        technique,
+        cells,
+        nums,
+        placements: Vec::new(),
+        eliminations,
+        detail,
+    }
+}
+
+/// Every almost locked set in the grid. A set that lies in more than one
+/// house is only listed once.
+pub fn almost_locked_sets(grid: &Grid) -> Vec<Als> {
+    let mut sets: Vec<Als> = Vec::new();
+    for house in houses() {
+        let empty: Vec<(Coord, PossibleNums)> = house
+            .iter()
+            .filter_map(|coord| match grid.get(*coord) {
+                Cell::Empty(possible_nums) => Some((*coord, possible_nums)),
+                Cell::Fixed(_) => None,
+            })
+            .collect();
+
+        // A set of every empty cell would have as many numbers as cells.
+        for size in 1..empty.len() {
+            for subset in combinations(&empty, size) {
+                let nums = subset
+                    .iter()
+                    .fold(PossibleNums::NONE, |nums, (_, possible_nums)| {
+                        nums.union(*possible_nums)
+                    });
+                if nums.len() as usize != size + 1 {
+                    continue;
+                }
+
+                let mut cells: Vec<Coord> = subset.iter().map(|(coord, _)| *coord).collect();
+                cells.sort_by_key(|coord| (coord.parent_row(), coord.parent_col()));
+                let als = Als { cells, nums };
+                if !sets.contains(&als) {
+                    sets.push(als);
+                }
+            }
+        }
+    }
+
+    sets
+}
+
+/// Every ALS-XZ and Death Blossom in the grid that eliminates at least one
+/// possible number.
+pub fn als(grid: &Grid) -> Vec<Step> {
+    let sets = almost_locked_sets(grid);
+    let mut steps = als_xz(grid, &sets);
+    steps.extend(death_blossoms(grid, &sets));
+    steps
+}
+
+/// Two almost locked sets with no cells in common, and a restricted common
+/// number `x`: every place for `x` in one sees every place in the other, so
+/// `x` goes in at most one of them. The other set is then locked, so every
+/// other number they share goes in one of them and is removed from the
+/// cells that see all of its places in both.
+fn als_xz(grid: &Grid, sets: &[Als]) -> Vec<Step> {
+    let mut steps = Vec::new();
+    for (i, a) in sets.iter().enumerate() {
+        for b in &sets[i + 1..] {
+            if a.cells.iter().any(|coord| b.cells.contains(coord)) {
+                continue;
+            }
+            let common = a.nums.intersection(b.nums);
+            if common.len() < 2 {
+                continue;
+            }
+
+            for x in common {
+                let (a_places, b_places) = (a.places(grid, x), b.places(grid, x));
+                if !a_places.iter().all(|a| b_places.iter().all(|b| a.sees(b))) {
+                    continue;
+                }
+
+                let mut eliminations = Vec::new();
+                for z in common.iter().filter(|z| *z != x) {
+                    let mut z_places = a.places(grid, z);
+                    z_places.extend(b.places(grid, z));
+                    eliminations.extend(removed_by(grid, z, &z_places, &[a, b]));
+                }
+
+                if !eliminations.is_empty() {
+                    let mut cells = a.cells.clone();
+                    cells.extend(&b.cells);
+                    steps.push(Step {
+                        technique: Technique::AlsXz,
+                        cells,
+                        nums: a.nums.union(b.nums),
+                        placements: Vec::new(),
+                        eliminations,
+                        detail: Detail::AlsXz {
+                            sets: [a.clone(), b.clone()],
+                            restricted: x,
+                        },
+                    });
+                }
+            }
+        }
+    }
+
+    steps
+}
+
+/// A stem cell with two or three possible numbers, and for each of them a
+/// petal: an almost locked set in which every place for that number sees
+/// the stem. Whichever number the stem holds, its petal is locked, so a
+/// number `z` that every petal shares and the stem doesn't goes in one of
+/// the petals, and is removed from the cells that see all of its places.
+fn death_blossoms(grid: &Grid, sets: &[Als]) -> Vec<Step> {
+    let mut steps = Vec::new();
+    for (stem, stem_nums) in candidate_cells(grid) {
+        if !(2..=3).contains(&stem_nums.len()) {
+            continue;
+        }
+
+        // The sets that can be the petal for each of the stem's numbers.
+        let petals: Vec<(Number, Vec<&Als>)> = stem_nums
+            .iter()
+            .map(|num| {
+                let sets = sets
+                    .iter()
+                    .filter(|als| {
+                        als.nums.contains(num)
+                            && !als.cells.contains(&stem)
+                            && als.places(grid, num).iter().all(|coord| coord.sees(&stem))
+                    })
+                    .collect();
+                (num, sets)
+            })
+            .collect();
+
+        let mut chosen: Vec<(Number, &Als)> = Vec::new();
+        choose_petals(grid, stem, stem_nums, &petals, &mut chosen, &mut steps);
+    }
+
+    steps
+}
+
+/// Try every way of picking one petal for each of the stem's numbers, with
+/// no two petals sharing a cell, adding a step for each that eliminates
+/// something.
+fn choose_petals<'a>(
+    grid: &Grid,
+    stem: Coord,
+    stem_nums: PossibleNums,
+    petals: &[(Number, Vec<&'a Als>)],
+    chosen: &mut Vec<(Number, &'a Als)>,
+    steps: &mut Vec<Step>,
+) {
+    let Some(((num, options), rest)) = petals.split_first() else {
+        let common = chosen.iter().fold(PossibleNums::ALL, |common, (_, als)| {
+            common.intersection(als.nums)
+        });
+        let sets: Vec<&Als> = chosen.iter().map(|(_, als)| *als).collect();
+
+        let mut eliminations = Vec::new();
+        for z in common.iter().filter(|z| !stem_nums.contains(*z)) {
+            let z_places: Vec<Coord> = sets.iter().flat_map(|als| als.places(grid, z)).collect();
+            eliminations.extend(
+                removed_by(grid, z, &z_places, &sets)
+                    .into_iter()
+                    .filter(|elimination| elimination.coord != stem),
+            );
+        }
+
+        if !eliminations.is_empty() {
+            let mut cells = vec![stem];
+            cells.extend(sets.iter().flat_map(|als| als.cells.iter().copied()));
+            steps.push(Step {
+                technique: Technique::DeathBlossom,
+                cells,
+                nums: sets
+                    .iter()
+                    .fold(stem_nums, |nums, als| nums.union(als.nums)),
+                placements: Vec::new(),
+                eliminations,
+                detail: Detail::DeathBlossom {
+                    stem,
+                    petals: chosen
+                        .iter()
+                        .map(|(num, als)| (*num, (*als).clone()))
+                        .collect(),
+                },
+            });
+        }
+        return;
+    };
+
+    for als in options {
+        let overlaps = chosen
+            .iter()
+            .any(|(_, other)| other.cells.iter().any(|coord| als.cells.contains(coord)));
+        if !overlaps {
+            chosen.push((*num, als));
+            choose_petals(grid, stem, stem_nums, rest, chosen, steps);
+            chosen.pop();
+        }
+    }
+}
+
+/// Remove `num` from the cells outside `sets` that see all of its `places`.
+fn removed_by(grid: &Grid, num: Number, places: &[Coord], sets: &[&Als]) -> Vec<Elimination> {
+    if places.is_empty() {
+        return Vec::new();
+    }
+
+    candidate_cells(grid)
+        .filter(|(coord, possible_nums)| {
+            possible_nums.contains(num)
+                && sets.iter().all(|als| !als.cells.contains(coord))
+                && places.iter().all(|place| coord.sees(place))
+        })
+        .map(|(coord, _)| Elimination { coord, num })
+        .collect()
+}
+
+/// Every empty cell with its possible numbers.
+fn candidate_cells(grid: &Grid) -> impl Iterator<Item = (Coord, PossibleNums)> + '_ {
+    (0..9)
+        .flat_map(row_coords)
+        .filter_map(|coord| match grid.get(coord) {
+            Cell::Empty(possible_nums) => Some((coord, possible_nums)),
+            Cell::Fixed(_) => None,
+        })
+}
+
+/// Every way of choosing `size` of `items`, keeping their order.
+fn combinations<T: Copy>(items: &[T], size: usize) -> Vec<Vec<T>> {
+    if size == 0 {
+        return vec![Vec::new()];
+    }
+    if items.len() < size {
+        return Vec::new();
+    }
+
+    let mut result = Vec::new();
+    for (i, item) in items.iter().enumerate() {
+        for mut rest in combinations(&items[i + 1..], size - 1) {
+            rest.insert(0, *item);
+            result.push(rest);
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::smart_backtrack::Propagation;
+
+    fn nums(nums: &[u8]) -> PossibleNums {
+        let mut possible_nums = PossibleNums::NONE;
+        for num in nums {
+            possible_nums.insert(Number::from_u8_normalize(*num));
+        }
+        possible_nums
+    }
+
+    fn empty(possible: &[u8]) -> Cell {
+        Cell::Empty(nums(possible))
+    }
+
+    fn fixed(num: u8) -> Cell {
+        Cell::Fixed(Number::from_u8_normalize(num))
+    }
+
+    fn elimination(row: u8, col: u8, num: u8) -> Elimination {
+        Elimination {
+            coord: Coord::from_row_col(row, col),
+            num: Number::from_u8_normalize(num),
+        }
+    }
+
+    /// A step that only removes numbers.
+    fn step(
+        technique: Technique,
+        cells: Vec<Coord>,
+        possible: &[u8],
+        eliminations: Vec<Elimination>,
+    ) -> Step {
+        Step {
+            technique,
+            cells,
+            nums: nums(possible),
+            placements: Vec::new(),
+            eliminations,
+            detail: Detail::None,
+        }
+    }
+
+    #[test]
+    fn test_combinations() {
+        assert_eq!(
+            combinations(&[1, 2, 3, 4], 3),
+            vec![vec![1, 2, 3], vec![1, 2, 4], vec![1, 3, 4], vec![2, 3, 4]]
+        );
+        assert!(combinations(&[1, 2], 3).is_empty());
+    }
+
+    #[test]
+    fn test_naked_subsets() {
+        let coords = row_coords(0);
+        let house = [
+            empty(&[1, 5]),
+            empty(&[1, 5]),
+            empty(&[1, 2, 5]),
+            fixed(3),
+            empty(&[4, 6, 8, 9]),
+            empty(&[4, 6, 7]),
+            empty(&[6, 7]),
+            empty(&[4, 7]),
+            empty(&[8, 9]),
+        ];
+
+        assert_eq!(
+            naked_subsets(&house, &coords, 2),
+            vec![step(
+                Technique::NakedPair,
+                vec![coords[0], coords[1]],
+                &[1, 5],
+                vec![elimination(0, 2, 1), elimination(0, 2, 5)],
+            )]
+        );
+
+        // The first three cells hold 1, 2 and 5, but no other cell can.
+        assert_eq!(
+            naked_subsets(&house, &coords, 3),
+            vec![step(
+                Technique::NakedTriple,
+                vec![coords[5], coords[6], coords[7]],
+                &[4, 6, 7],
+                vec![elimination(0, 4, 4), elimination(0, 4, 6)],
+            )]
+        );
+    }
+
+    #[test]
+    fn test_hidden_subsets() {
+        let coords = col_coords(4);
+        let house = [
+            empty(&[1, 2, 3, 8, 9]),
+            empty(&[1, 2, 4, 8, 9]),
+            empty(&[1, 3, 4]),
+            empty(&[2, 3, 4]),
+            empty(&[5, 6, 7]),
+            empty(&[5, 6, 7]),
+            empty(&[5, 6, 7]),
+            empty(&[1, 2, 3, 4]),
+            empty(&[1, 2, 3, 4]),
+        ];
+
+        assert_eq!(
+            hidden_subsets(&house, &coords, 2),
+            vec![step(
+                Technique::HiddenPair,
+                vec![coords[0], coords[1]],
+                &[8, 9],
+                vec![
+                    elimination(0, 4, 1),
+                    elimination(0, 4, 2),
+                    elimination(0, 4, 3),
+                    elimination(1, 4, 1),
+                    elimination(1, 4, 2),
+                    elimination(1, 4, 4),
+                ],
+            )]
+        );
+
+        // 5, 6 and 7 already fill their cells, so there is nothing to remove.
+        assert!(hidden_subsets(&house, &coords, 3).is_empty());
+    }
+
+    /// An empty grid with the given possible numbers in some cells.
+    fn grid_with(cells: &[((u8, u8), &[u8])]) -> Grid {
+        let mut grid = Grid::try_from_line_str(&".".repeat(81)).unwrap();
+        for ((row, col), possible) in cells {
+            grid.set(Coord::from_row_col(*row, *col), empty(possible));
+        }
+        grid
+    }
+
+    /// Every number but `num`.
+    fn all_but(num: u8) -> Vec<u8> {
+        (1..=9).filter(|other| *other != num).collect()
+    }
+
+    /// A house and the only cells in it that a number can go in.
+    type Places<'a> = ([Coord; 9], &'a [(u8, u8)]);
+
+    /// An empty grid where `num` can only go in the given cells of each
+    /// house.
+    fn grid_with_places(num: u8, places: &[Places]) -> Grid {
+        let without = all_but(num);
+        let cells: Vec<((u8, u8), &[u8])> = places
+            .iter()
+            .flat_map(|(house, keep)| {
+                house
+                    .iter()
+                    .map(|coord| (coord.parent_row(), coord.parent_col()))
+                    .filter(|position| !keep.contains(position))
+            })
+            .map(|position| (position, without.as_slice()))
+            .collect();
+        grid_with(&cells)
+    }
+
+    #[test]
+    fn test_pointing() {
+        // 5 can only go in the top row of the first box.
+        let grid = grid_with_places(5, &[(box_coords(0), &[(0, 0), (0, 1)])]);
+
+        assert_eq!(
+            intersections(&grid),
+            vec![step(
+                Technique::Pointing,
+                vec![Coord::from_row_col(0, 0), Coord::from_row_col(0, 1)],
+                &[5],
+                (3..9).map(|col| elimination(0, col, 5)).collect(),
+            )]
+        );
+    }
+
+    #[test]
+    fn test_box_line_reduction() {
+        // 7 can only go in the first box of the fourth row.
+        let grid = grid_with_places(7, &[(row_coords(3), &[(3, 0), (3, 1), (3, 2)])]);
+
+        let steps = intersections(&grid);
+        let eliminations = [4, 5]
+            .into_iter()
+            .flat_map(|row| (0..3).map(move |col| elimination(row, col, 7)))
+            .collect();
+        assert_eq!(
+            steps,
+            vec![step(
+                Technique::BoxLineReduction,
+                row_coords(3)[0..3].to_vec(),
+                &[7],
+                eliminations,
+            )]
+        );
+
+        let mut applied = grid;
+        steps[0].apply(&mut applied);
+        assert!(intersections(&applied).is_empty());
+    }
+
+    /// Prune a real puzzle with every propagation technique, so only the
+    /// harder techniques have anything left to find.
+    fn pruned(line: &str) -> Grid {
+        let mut grid = Grid::try_from_line_str(line).unwrap();
+        grid.prune_with(Propagation {
+            hidden_singles: true,
+            subsets: true,
+            intersections: true,
+        })
+        .unwrap();
+        grid
+    }
+
+    /// Check that no step removes a number from the cell that holds it in
+    /// the solution.
+    fn assert_sound(grid: &Grid, steps: &[Step]) {
+        let mut solution = *grid;
+        solution.solve_strict().unwrap();
+        for step in steps {
+            for elimination in &step.eliminations {
+                assert_ne!(
+                    solution.get(elimination.coord),
+                    Cell::Fixed(elimination.num),
+                    "{}",
+                    step
+                );
+            }
+        }
+    }
+
+    /// Check that `steps` is a single step of `technique` with the given
+    /// eliminations, and return it.
+    fn only_step(steps: Vec<Step>, technique: Technique, eliminations: Vec<Elimination>) -> Step {
+        assert_eq!(steps.len(), 1, "{:?}", steps);
+        let [step] = <[Step; 1]>::try_from(steps).unwrap();
+        assert_eq!(step.technique, technique);
+        assert_eq!(step.eliminations, eliminations);
+        step
+    }
+
+    /// Run `find` on a pruned real puzzle, check that it finds `technique`
+    /// and that every step is sound, and return the steps.
+    fn assert_finds(
+        line: &str,
+        find: impl Fn(&Grid) -> Vec<Step>,
+        technique: Technique,
+    ) -> Vec<Step> {
+        let grid = pruned(line);
+        let steps = find(&grid);
+        assert!(
+            steps.iter().any(|step| step.technique == technique),
+            "{:?}",
+            technique
+        );
+        assert_sound(&grid, &steps);
+        steps
+    }
+
+    #[test]
+    fn test_x_wing() {
+        let steps = assert_finds(
+            "100000569492056108056109240009640801064010000218035604040500016905061402621000005",
+            fish,
+            Technique::XWing,
+        );
+
+        assert_eq!(
+            steps[0],
+            step(
+                Technique::XWing,
+                vec![
+                    Coord::from_row_col(1, 3),
+                    Coord::from_row_col(1, 7),
+                    Coord::from_row_col(5, 3),
+                    Coord::from_row_col(5, 7),
+                ],
+                &[7],
+                vec![
+                    elimination(0, 3, 7),
+                    elimination(4, 3, 7),
+                    elimination(7, 3, 7),
+                    elimination(8, 3, 7),
+                    elimination(7, 7, 7),
+                    elimination(8, 7, 7),
+                ],
+            )
+        );
+    }
+
+    #[test]
+    fn test_swordfish() {
+        let steps = assert_finds(
+            "529410703006003002003200000052300076637050200190627530300069420200830600960742305",
+            fish,
+            Technique::Swordfish,
+        );
+        assert_eq!(
+            steps[0],
+            step(
+                Technique::Swordfish,
+                [1, 2, 3]
+                    .into_iter()
+                    .flat_map(|row| [0, 4, 6].map(|col| Coord::from_row_col(row, col)))
+                    .collect(),
+                &[8],
+                vec![
+                    elimination(1, 1, 8),
+                    elimination(1, 7, 8),
+                    elimination(2, 1, 8),
+                    elimination(2, 8, 8),
+                    elimination(3, 5, 8),
+                ],
+            )
+        );
+    }
+
+    #[test]
+    fn test_jellyfish() {
+        // 3 can only go in the first four columns of the first four rows.
+        let keep: Vec<Vec<(u8, u8)>> = (0..4)
+            .map(|row| (0..4).map(|col| (row, col)).collect())
+            .collect();
+        let places: Vec<Places> = (0..4)
+            .map(|row| (row_coords(row), keep[row as usize].as_slice()))
+            .collect();
+        let grid = grid_with_places(3, &places);
+
+        let step = only_step(
+            fish(&grid),
+            Technique::Jellyfish,
+            (0..4)
+                .flat_map(|col| (4..9).map(move |row| elimination(row, col, 3)))
+                .collect(),
+        );
+        assert_eq!(step.cells.len(), 16);
+    }
+
+    #[test]
+    fn test_xy_wing() {
+        let grid = grid_with(&[((0, 0), &[1, 2]), ((0, 4), &[1, 3]), ((4, 0), &[2, 3])]);
+
+        let steps = wings(&grid);
+        assert_eq!(
+            steps,
+            vec![Step {
+                detail: Detail::Wing {
+                    pivot: vec![Coord::from_row_col(0, 0)],
+                    pincers: [Coord::from_row_col(0, 4), Coord::from_row_col(4, 0)],
+                },
+                ..step(
+                    Technique::XyWing,
+                    vec![
+                        Coord::from_row_col(0, 0),
+                        Coord::from_row_col(0, 4),
+                        Coord::from_row_col(4, 0),
+                    ],
+                    &[1, 2, 3],
+                    vec![elimination(4, 4, 3)],
+                )
+            }]
+        );
+        assert_eq!(
+            steps[0].to_string(),
+            "XY-Wing 1/2/3 with pivot r1c1 and pincers r1c5, r5c1: r5c5<>3"
+        );
+    }
+
+    #[test]
+    fn test_xyz_wing() {
+        let grid = grid_with(&[((0, 0), &[1, 2, 3]), ((0, 4), &[1, 3]), ((1, 1), &[2, 3])]);
+
+        only_step(
+            wings(&grid),
+            Technique::XyzWing,
+            vec![elimination(0, 1, 3), elimination(0, 2, 3)],
+        );
+    }
+
+    #[test]
+    fn test_w_wing() {
+        // 4 can only go in r3c3 and r5c3 in the third column.
+        let mut grid = grid_with_places(4, &[(col_coords(2), &[(2, 2), (4, 2)])]);
+        grid.set(Coord::from_row_col(0, 0), empty(&[4, 5]));
+        grid.set(Coord::from_row_col(4, 1), empty(&[4, 5]));
+
+        let step = only_step(
+            wings(&grid),
+            Technique::WWing,
+            vec![
+                elimination(0, 1, 5),
+                elimination(1, 1, 5),
+                elimination(2, 1, 5),
+                elimination(3, 0, 5),
+                elimination(4, 0, 5),
+                elimination(5, 0, 5),
+            ],
+        );
+        assert_eq!(
+            step.detail,
+            Detail::Wing {
+                pivot: vec![Coord::from_row_col(2, 2), Coord::from_row_col(4, 2)],
+                pincers: [Coord::from_row_col(0, 0), Coord::from_row_col(4, 1)],
+            }
+        );
+    }
+
+    #[test]
+    fn test_wings_are_sound() {
+        assert_finds(
+            "900040000000600031020000090000700020002935600070002000060000073510009000000080009",
+            wings,
+            Technique::XyWing,
+        );
+    }
+
+    #[test]
+    fn test_color_trap() {
+        let grid = grid_with_places(
+            1,
+            &[
+                (row_coords(0), &[(0, 0), (0, 4)]),
+                (col_coords(4), &[(0, 4), (4, 4)]),
+                (box_coords(4), &[(4, 4), (5, 5)]),
+            ],
+        );
+
+        let steps = coloring(&grid);
+        assert_eq!(
+            steps,
+            vec![Step {
+                detail: Detail::Coloring {
+                    colors: [
+                        vec![Coord::from_row_col(0, 0), Coord::from_row_col(4, 4)],
+                        vec![Coord::from_row_col(0, 4), Coord::from_row_col(5, 5)],
+                    ],
+                },
+                ..step(
+                    Technique::ColorTrap,
+                    vec![
+                        Coord::from_row_col(0, 0),
+                        Coord::from_row_col(0, 4),
+                        Coord::from_row_col(4, 4),
+                        Coord::from_row_col(5, 5),
+                    ],
+                    &[1],
+                    vec![elimination(5, 0, 1)],
+                )
+            }]
+        );
+        assert_eq!(
+            steps[0].to_string(),
+            "Color Trap 1 with colors r1c1, r5c5 and r1c5, r6c6: r6c1<>1"
+        );
+    }
+
+    #[test]
+    fn test_color_wrap() {
+        // r1c1, r5c5 and r2c2 share a color, and the first and last are in
+        // the same box.
+        let grid = grid_with_places(
+            1,
+            &[
+                (row_coords(0), &[(0, 0), (0, 4)]),
+                (col_coords(4), &[(0, 4), (4, 4)]),
+                (row_coords(4), &[(4, 1), (4, 4)]),
+                (col_coords(1), &[(1, 1), (4, 1)]),
+            ],
+        );
+
+        only_step(
+            coloring(&grid),
+            Technique::ColorWrap,
+            vec![
+                elimination(0, 0, 1),
+                elimination(1, 1, 1),
+                elimination(4, 4, 1),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_grade() {
+        let puzzle = Grid::try_from_line_str(
+            "003020600900305001001806400008102900700000008006708200002609500800203009005010300",
+        )
+        .unwrap();
+        assert_eq!(explain(&puzzle, &Options::default()).grade, Grade::Singles);
+
+        let puzzle = Grid::try_from_csv_str(crate::puzzles::OK).unwrap();
+        assert_eq!(
+            explain(&puzzle, &Options::default()).grade,
+            Grade::Technique(Technique::NakedPair)
+        );
+
+        let puzzle = Grid::try_from_line_str(
+            "100000569492056108056109240009640801064010000218035604040500016905061402621000005",
+        )
+        .unwrap();
+        assert_eq!(
+            explain(&puzzle, &Options::default()).grade,
+            Grade::Technique(Technique::XWing)
+        );
+        assert_eq!(
+            explain(&puzzle, &Options::default()).grade.to_string(),
+            "X-Wing"
+        );
+    }
+
+    #[test]
+    fn test_singles() {
+        // 1 has only r1c1 left in the first row, and r5c5 has only 2 left.
+        let mut grid = grid_with_places(1, &[(row_coords(0), &[(0, 0)])]);
+        grid.set(Coord::from_row_col(4, 4), empty(&[2]));
+
+        let steps = singles(&grid);
+        assert_eq!(steps.len(), 2);
+        assert_eq!(steps[0].to_string(), "Naked Single 2 in r5c5: r5c5=2");
+        assert_eq!(steps[1].to_string(), "Hidden Single 1 in row 1: r1c1=1");
+
+        let mut applied = grid;
+        steps[0].apply(&mut applied);
+        assert_eq!(applied.get(Coord::from_row_col(4, 4)), fixed(2));
+        assert_eq!(
+            applied.get(Coord::from_row_col(4, 0)),
+            empty(&[1, 3, 4, 5, 6, 7, 8, 9])
+        );
+    }
+
+    #[test]
+    fn test_explain() {
+        let puzzle = Grid::try_from_csv_str(crate::puzzles::OK).unwrap();
+        let mut solution = puzzle;
+        solution.solve_strict().unwrap();
+
+        let explanation = explain(&puzzle, &Options::default());
+        assert_eq!(explanation.grid, solution);
+        assert_eq!(explanation.grade, Grade::Technique(Technique::NakedPair));
+        for step in &explanation.steps {
+            for placement in &step.placements {
+                assert_eq!(
+                    solution.get(placement.coord),
+                    Cell::Fixed(placement.num),
+                    "{}",
+                    step
+                );
+            }
+        }
+        assert_sound(&puzzle, &explanation.steps);
+
+        let placements: usize = explanation
+            .steps
+            .iter()
+            .map(|step| step.placements.len())
+            .sum();
+        assert_eq!(placements, 81 - puzzle.clue_count());
+
+        // With no clues there is nothing to reason from.
+        let explanation = explain(&grid_with(&[]), &Options::default());
+        assert!(explanation.steps.is_empty());
+        assert_eq!(explanation.grade, Grade::RequiresGuessing);
+    }
+
+    /// Explain a real puzzle and check that it is solved with every
+    /// technique in `techniques`, the last being the hardest, and that no
+    /// step removes a number from the solution.
+    fn assert_explains(line: &str, techniques: &[Technique]) {
+        let puzzle = Grid::try_from_line_str(line).unwrap();
+        let mut solution = puzzle;
+        solution.solve_strict().unwrap();
+
+        let explanation = explain(&puzzle, &Options::default());
+        assert_eq!(explanation.grid, solution);
+        assert_eq!(
+            explanation.grade,
+            Grade::Technique(*techniques.last().unwrap())
+        );
+        for technique in techniques {
+            assert!(
+                explanation
+                    .steps
+                    .iter()
+                    .any(|step| step.technique == *technique),
+                "{:?}",
+                technique
+            );
+        }
+        assert_sound(&puzzle, &explanation.steps);
+    }
+
+    #[test]
+    fn test_explain_larger_techniques() {
+        assert_explains(
+            "000704005020010070000080002090006250600070008053200010400090000030060090200407000",
+            &[
+                Technique::XWing,
+                Technique::Swordfish,
+                Technique::XyzWing,
+                Technique::ColorTrap,
+                Technique::XChain,
+                Technique::XyChain,
+            ],
+        );
+        assert_explains(
+            "48.3............71.2.......7.5....6....2..8.............1.76...3.....4......5....",
+            &[Technique::XyWing, Technique::WWing],
+        );
+    }
+
+    #[test]
+    fn test_next_step() {
+        let grid = pruned(
+            "529410703006003002003200000052300076637050200190627530300069420200830600960742305",
+        );
+        let step = next_step(&grid, &Options::default()).unwrap();
+        assert_eq!(step.technique, Technique::Swordfish);
+    }
+
+    #[test]
+    fn test_xy_chain() {
+        let grid = grid_with(&[((0, 0), &[1, 2]), ((0, 4), &[2, 3]), ((4, 4), &[1, 3])]);
+
+        let step = only_step(
+            chains(&grid, 6),
+            Technique::XyChain,
+            vec![elimination(4, 0, 1)],
+        );
+        assert_eq!(
+            step.to_string(),
+            "XY-Chain (1)r1c1=(2)r1c1-(2)r1c5=(3)r1c5-(3)r5c5=(1)r5c5: r5c1<>1"
+        );
+        let Detail::Chain(chain) = &step.detail else {
+            panic!("{:?}", step.detail);
+        };
+        assert_eq!(
+            chain[0],
+            ChainNode {
+                coord: Coord::from_row_col(0, 0),
+                num: Number::One,
+                link: Some(Link::Strong),
+            }
+        );
+        assert_eq!(chain[5].link, None);
+
+        // The chain has six numbers.
+        assert!(chains(&grid, 5).is_empty());
+        assert!(chains(&grid, 0).is_empty());
+    }
+
+    #[test]
+    fn test_chains_are_sound() {
+        let line =
+            "100000569492056108056109240009640801064010000218035604040500016905061402621000005";
+
+        // The X-Wing is also an X-Chain of four numbers.
+        let steps = assert_finds(line, |grid| chains(grid, 4), Technique::XChain);
+        assert!(steps.iter().any(|step| step.technique == Technique::XChain
+            && step.eliminations.contains(&elimination(0, 3, 7))));
+
+        assert_finds(line, |grid| chains(grid, 12), Technique::Aic);
+        let steps = assert_finds(
+            line,
+            |grid| forcing_chains(grid, 12),
+            Technique::CellForcingChain,
+        );
+        assert!(steps
+            .iter()
+            .any(|step| step.technique == Technique::UnitForcingChain));
+    }
+
+    #[test]
+    fn test_almost_locked_sets() {
+        let grid = grid_with(&[((0, 0), &[1, 2]), ((0, 4), &[1, 3]), ((1, 5), &[2, 3])]);
+
+        let sets = almost_locked_sets(&grid);
+        let pair = Als {
+            cells: vec![Coord::from_row_col(0, 4), Coord::from_row_col(1, 5)],
+            nums: nums(&[1, 2, 3]),
+        };
+        assert!(sets.contains(&pair));
+        assert_eq!(pair.to_string(), "r1c5, r2c6 (1/2/3)");
+
+        // A bivalue cell is in three houses but only listed once.
+        let cell = Als {
+            cells: vec![Coord::from_row_col(0, 0)],
+            nums: nums(&[1, 2]),
+        };
+        assert_eq!(sets.iter().filter(|als| **als == cell).count(), 1);
+    }
+
+    #[test]
+    fn test_als_xz() {
+        let grid = grid_with(&[((0, 0), &[1, 2]), ((0, 4), &[1, 3]), ((1, 5), &[2, 3])]);
+
+        let steps = als(&grid);
+        let step = steps
+            .iter()
+            .find(|step| {
+                step.technique == Technique::AlsXz
+                    && step.cells.len() == 3
+                    && step.cells.contains(&Coord::from_row_col(0, 0))
+            })
+            .unwrap();
+        assert_eq!(
+            step.detail,
+            Detail::AlsXz {
+                sets: [
+                    Als {
+                        cells: vec![Coord::from_row_col(0, 0)],
+                        nums: nums(&[1, 2]),
+                    },
+                    Als {
+                        cells: vec![Coord::from_row_col(0, 4), Coord::from_row_col(1, 5)],
+                        nums: nums(&[1, 2, 3]),
+                    },
+                ],
+                restricted: Number::from_u8_normalize(1),
+            }
+        );
+        assert_eq!(
+            step.to_string(),
+            "ALS-XZ r1c1 (1/2) and r1c5, r2c6 (1/2/3) with restricted common 1: \
+             r1c4<>2, r1c6<>2, r2c1<>2, r2c2<>2, r2c3<>2"
+        );
+    }
+
+    #[test]
+    fn test_death_blossom() {
+        let grid = grid_with(&[
+            ((4, 4), &[1, 2, 3]),
+            ((4, 8), &[1, 4]),
+            ((4, 0), &[2, 4]),
+            ((3, 3), &[3, 4]),
+        ]);
+
+        let steps = als(&grid);
+        let step = steps
+            .iter()
+            .find(|step| step.technique == Technique::DeathBlossom && step.cells.len() == 4)
+            .unwrap();
+        assert_eq!(
+            step.to_string(),
+            "Death Blossom with stem r5c5 and petals 1 r5c9 (1/4); 2 r5c1 (2/4); \
+             3 r4c4 (3/4): r5c4<>4, r5c6<>4"
+        );
+    }
+
+    #[test]
+    fn test_als_is_sound() {
+        for line in [
+            "900040000000600031020000090000700020002935600070002000060000073510009000000080009",
+            "100000569492056108056109240009640801064010000218035604040500016905061402621000005",
+        ] {
+            let grid = pruned(line);
+            let steps = als(&grid);
+            assert!(!steps.is_empty());
+            assert_sound(&grid, &steps);
+        }
+    }
+
+    fn corners() -> Vec<Coord> {
+        vec![
+            Coord::from_row_col(0, 0),
+            Coord::from_row_col(0, 3),
+            Coord::from_row_col(1, 0),
+            Coord::from_row_col(1, 3),
+        ]
+    }
+
+    #[test]
+    fn test_unique_rectangle_type_1() {
+        let grid = grid_with(&[
+            ((0, 0), &[1, 2]),
+            ((0, 3), &[1, 2]),
+            ((1, 0), &[1, 2]),
+            ((1, 3), &[1, 2, 5]),
+        ]);
+
+        assert_eq!(
+            unique_rectangles(&grid),
+            vec![step(
+                Technique::UniqueRectangle1,
+                corners(),
+                &[1, 2],
+                vec![elimination(1, 3, 1), elimination(1, 3, 2)],
+            )]
+        );
+    }
+
+    #[test]
+    fn test_unique_rectangle_type_2() {
+        let grid = grid_with(&[
+            ((0, 0), &[1, 2]),
+            ((0, 3), &[1, 2]),
+            ((1, 0), &[1, 2, 7]),
+            ((1, 3), &[1, 2, 7]),
+        ]);
+
+        only_step(
+            unique_rectangles(&grid),
+            Technique::UniqueRectangle2,
+            [1, 2, 4, 5, 6, 7, 8]
+                .map(|col| elimination(1, col, 7))
+                .to_vec(),
+        );
+    }
+
+    #[test]
+    fn test_unique_rectangle_type_3() {
+        // The roof acts as a cell with 3 and 4, forming a naked pair with
+        // the last cell of the row.
+        let grid = grid_with(&[
+            ((0, 0), &[1, 2]),
+            ((0, 3), &[1, 2]),
+            ((1, 0), &[1, 2, 3]),
+            ((1, 3), &[1, 2, 4]),
+            ((1, 8), &[3, 4]),
+        ]);
+
+        let step = only_step(
+            unique_rectangles(&grid),
+            Technique::UniqueRectangle3,
+            [1, 2, 4, 5, 6, 7]
+                .into_iter()
+                .flat_map(|col| [elimination(1, col, 3), elimination(1, col, 4)])
+                .collect(),
+        );
+        assert_eq!(step.cells.last(), Some(&Coord::from_row_col(1, 8)));
+    }
+
+    #[test]
+    fn test_unique_rectangle_type_4() {
+        // 1 can only go in the roof of the second row.
+        let mut grid = grid_with_places(1, &[(row_coords(1), &[(1, 0), (1, 3)])]);
+        grid.set(Coord::from_row_col(0, 0), empty(&[1, 2]));
+        grid.set(Coord::from_row_col(0, 3), empty(&[1, 2]));
+        grid.set(Coord::from_row_col(1, 0), empty(&[1, 2, 7, 8]));
+        grid.set(Coord::from_row_col(1, 3), empty(&[1, 2, 8, 9]));
+
+        assert_eq!(
+            unique_rectangles(&grid),
+            vec![step(
+                Technique::UniqueRectangle4,
+                corners(),
+                &[1, 2],
+                vec![elimination(1, 0, 2), elimination(1, 3, 2)],
+            )]
+        );
+    }
+
+    #[test]
+    fn test_bug_plus_one() {
+        // Leave 1 and 2, and 3 and 4, to be swapped in a solved grid, with
+        // 3 also possible in one of the cells holding 1.
+        let mut grid = Grid::try_from_csv_str(crate::puzzles::OK).unwrap();
+        grid.solve().unwrap();
+        let mut cells: Vec<((u8, u8), &[u8])> = Vec::new();
+        for coord in (0..9).flat_map(row_coords) {
+            let position = (coord.parent_row(), coord.parent_col());
+            match grid.get(coord) {
+                Cell::Fixed(Number::One | Number::Two) => cells.push((position, &[1, 2])),
+                Cell::Fixed(Number::Three | Number::Four) => cells.push((position, &[3, 4])),
+                _ => {}
+            }
+        }
+        let bug = cells
+            .iter()
+            .position(|(position, _)| {
+                grid.get(Coord::from_row_col(position.0, position.1)) == Cell::Fixed(Number::One)
+            })
+            .unwrap();
+        cells[bug].1 = &[1, 2, 3];
+        let (row, col) = cells[bug].0;
+        for ((row, col), possible) in cells {
+            grid.set(Coord::from_row_col(row, col), empty(possible));
+        }
+
+        assert_eq!(
+            bug_plus_one(&grid),
+            vec![step(
+                Technique::BugPlusOne,
+                vec![Coord::from_row_col(row, col)],
+                &[1, 2, 3],
+                vec![elimination(row, col, 1), elimination(row, col, 2)],
+            )]
+        );
+
+        // Without the third number it is a deadly pattern with two
+        // solutions, which BUG+1 doesn't apply to.
+        grid.set(Coord::from_row_col(row, col), empty(&[1, 2]));
+        assert!(bug_plus_one(&grid).is_empty());
+    }
+
+    #[test]
+    fn test_uniqueness_is_optional() {
+        let grid = grid_with(&[
+            ((0, 0), &[1, 2]),
+            ((0, 3), &[1, 2]),
+            ((1, 0), &[1, 2]),
+            ((1, 3), &[1, 2, 5]),
+        ]);
+        let unique = Options {
+            assume_unique: true,
+            ..Options::default()
+        };
+
+        let find = FINDERS[5];
+        assert_eq!(find(&grid, &unique), uniqueness(&grid));
+        assert!(!find(&grid, &unique).is_empty());
+        assert!(find(&grid, &Options::default()).is_empty());
+    }
+
+    #[test]
+    fn test_step_display() {
+        let step = step(
+            Technique::NakedPair,
+            vec![Coord::from_row_col(0, 1), Coord::from_row_col(0, 6)],
+            &[1, 5],
+            vec![elimination(0, 2, 1), elimination(0, 2, 5)],
+        );
+        assert_eq!(
+            step.to_string(),
+            "Naked Pair 1/5 in r1c2, r1c7: r1c3<>1, r1c3<>5"
+        );
+    }
+
+    #[test]
+    fn test_subsets_apply() {
+        let mut grid = Grid::try_from_line_str(
+            "000000010400000000020000000000050407008000300001090000300400200050100000000806000",
+        )
+        .unwrap();
+        grid.prune().unwrap();
+
+        let steps = subsets(&grid);
+        assert!(!steps.is_empty());
+        for step in &steps {
+            for elimination in &step.eliminations {
+                assert!(
+                    matches!(grid.get(elimination.coord), Cell::Empty(possible_nums) if possible_nums.contains(elimination.num)),
+                    "{}",
+                    step
+                );
+            }
+        }
+
+        let mut solution = grid;
+        solution.solve().unwrap();
+        for step in &steps {
+            let mut applied = grid;
+            step.apply(&mut applied);
+            for elimination in &step.eliminations {
+                assert_ne!(
+                    solution.get(elimination.coord),
+                    Cell::Fixed(elimination.num),
+                    "{}",
+                    step
+                );
+            }
+            assert_ne!(applied, grid);
+        }
+    }
+}
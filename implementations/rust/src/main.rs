@@ -1,48 +1,355 @@
+mod bitmask;
+mod compare;
+mod corpus;
+mod dlx;
+mod explicit_stack;
+mod logic;
 mod number;
 mod possible_nums;
 mod puzzles;
+mod report;
 mod smart_backtrack;
+mod solver;
+mod stats;
 
-use crate::smart_backtrack::Grid as Puzzle;
+use crate::compare::{Baseline, Comparison};
+use crate::corpus::CorpusReader;
+use crate::report::{Format, Metadata, Record, Report};
+use crate::smart_backtrack::{Branching, Grid as Puzzle, Propagation, SolveStats};
+use crate::solver::Solver;
+use clap::builder::{PossibleValuesParser, RangedU64ValueParser};
 use clap::Parser;
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::Instant;
 
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
 #[command(propagate_version = true)]
 struct Cli {
+    /// Number of times to solve the built-in puzzle when no input file is
+    /// given
     #[arg(short, long, default_value_t = 1000)]
     count: u32,
 
+    /// Corpus file to benchmark, with one puzzle per line or blank-line
+    /// separated CSV blocks
+    #[arg(short, long)]
+    input: Option<PathBuf>,
+
     #[arg(short, long)]
     demo: bool,
+
+    /// Puzzle to solve in demo mode, as a single line of 81 characters
+    #[arg(short, long)]
+    puzzle: Option<String>,
+
+    /// In demo mode, list up to this many solutions of the puzzle
+    #[arg(long)]
+    solutions: Option<usize>,
+
+    /// Accept malformed CSV puzzles, padding or truncating them and treating
+    /// unrecognized values as empty cells
+    #[arg(long)]
+    lenient: bool,
+
+    /// Solving algorithms to use, separated by commas. Several solvers are
+    /// run over the same puzzles and compared side by side
+    #[arg(
+        short,
+        long,
+        value_delimiter = ',',
+        default_value = "smart-backtrack",
+        value_parser = PossibleValuesParser::new(solver::NAMES)
+    )]
+    solver: Vec<String>,
+
+    /// How the smart-backtrack solver picks the next cell to branch on
+    #[arg(long, value_enum, default_value_t = Branching::Linear)]
+    branching: Branching,
+
+    /// Make the propagation of the smart-backtrack and explicit-stack
+    /// solvers also fill cells that are the only place left for a number in
+    /// a row, column or box
+    #[arg(long)]
+    hidden_singles: bool,
+
+    /// Make the propagation of the smart-backtrack and explicit-stack
+    /// solvers also use naked and hidden pairs, triples and quads
+    #[arg(long)]
+    subsets: bool,
+
+    /// Make the propagation of the smart-backtrack and explicit-stack
+    /// solvers also use pointing and box/line reduction where a box meets a
+    /// row or column
+    #[arg(long)]
+    intersections: bool,
+
+    /// The most numbers a chain may have in logical steps
+    #[arg(
+        long,
+        default_value_t = logic::Options::default().max_chain_length,
+        value_parser = RangedU64ValueParser::<usize>::new().range(1..)
+    )]
+    max_chain_length: usize,
+
+    /// Let logical steps use techniques that are only valid for puzzles with
+    /// a single solution
+    #[arg(long)]
+    assume_unique: bool,
+
+    /// In demo mode, list the logical steps that solve the puzzle, from
+    /// singles up, and grade it by the hardest one, before solving
+    #[arg(long)]
+    explain: bool,
+
+    /// Fail puzzles that have more than one solution
+    #[arg(long)]
+    strict: bool,
+
+    /// Include the time spent parsing each puzzle in its solve time
+    #[arg(long)]
+    include_parse: bool,
+
+    /// Output format for benchmark results
+    #[arg(short, long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+
+    /// Results saved from an earlier run (JSON or CSV) to compare against
+    #[arg(short, long)]
+    baseline: Option<PathBuf>,
+
+    /// Percentage slowdown against the baseline that counts as a regression
+    #[arg(short, long, default_value_t = 5.0)]
+    threshold: f64,
 }
 
 fn main() {
     let cli = Cli::parse();
 
+    let propagation = Propagation {
+        hidden_singles: cli.hidden_singles,
+        subsets: cli.subsets,
+        intersections: cli.intersections,
+    };
+    let solvers = match solver::select(&cli.solver, cli.branching, propagation) {
+        Ok(solvers) => solvers,
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(2);
+        }
+    };
+
     if cli.demo {
-        demo();
+        demo(&cli, solvers[0].as_ref());
     } else {
-        benchmark(cli.count);
+        let (records, input) = match &cli.input {
+            Some(path) => (
+                benchmark_file(path, &cli, &solvers),
+                path.display().to_string(),
+            ),
+            None => (benchmark(&cli, &solvers), String::from("built-in")),
+        };
+
+        let report = Report::new(records, Metadata::collect(input));
+        if let Err(err) = report.write(cli.format, &mut io::stdout().lock()) {
+            eprintln!("Failed to write results: {}", err);
+            process::exit(1);
+        }
+
+        if let Some(path) = cli.baseline {
+            compare(&path, &report, cli.threshold, cli.format);
+        }
     }
 }
 
-fn benchmark(count: u32) {
-    // let puzzle_strs = [puzzles::OK; 1000];
-    let puzzle_strs = vec![puzzles::OK; count as usize];
+/// Compare a run against saved results, exiting with an error status if it
+/// regressed by more than `threshold` percent.
+fn compare(path: &Path, report: &Report, threshold: f64, format: Format) {
+    let baseline = match Baseline::load(path) {
+        Ok(baseline) => baseline,
+        Err(err) => {
+            eprintln!("{}: {}", path.display(), err);
+            process::exit(1);
+        }
+    };
+
+    if let Some(input) = &baseline.input {
+        if *input != report.metadata.input {
+            eprintln!(
+                "warning: baseline was run on {}, not {}",
+                input, report.metadata.input
+            );
+        }
+    }
+
+    let comparison = Comparison::new(&baseline.records, &report.records, threshold);
+    if comparison.is_empty() {
+        eprintln!(
+            "{}: no solved puzzles in common with this run, nothing to compare",
+            path.display()
+        );
+        process::exit(1);
+    }
 
-    let puzzles = puzzle_strs.iter().map(|input| Puzzle::from_csv_str(input));
+    // Keep machine-readable output on stdout parseable.
+    if format == Format::Text {
+        println!("\nCompared to {}:\n{}", path.display(), comparison);
+    } else {
+        eprintln!("Compared to {}:\n{}", path.display(), comparison);
+    }
 
-    for mut puzzle in puzzles {
-        puzzle.solve().unwrap();
+    if comparison.is_regression() {
+        process::exit(1);
     }
 }
 
-fn demo() {
-    let mut puzzle = Puzzle::from_csv_str(puzzles::OK);
+fn parse_csv(input: &str, lenient: bool) -> Result<Puzzle, String> {
+    if lenient {
+        Ok(Puzzle::from_csv_str_lenient(input))
+    } else {
+        Puzzle::try_from_csv_str(input).map_err(|err| err.to_string())
+    }
+}
+
+fn benchmark(cli: &Cli, solvers: &[Box<dyn Solver>]) -> Vec<Record> {
+    let puzzles = (0..cli.count).map(|_| parse_csv(puzzles::OK, cli.lenient));
+
+    run(puzzles, cli, solvers)
+}
+
+fn benchmark_file(path: &Path, cli: &Cli, solvers: &[Box<dyn Solver>]) -> Vec<Record> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(err) => {
+            eprintln!("{}: {}", path.display(), err);
+            process::exit(1);
+        }
+    };
+    let reader = CorpusReader::new(BufReader::new(file)).lenient(cli.lenient);
+    let puzzles = reader.map(|result| result.map_err(|err| format!("{}: {}", path.display(), err)));
+
+    run(puzzles, cli, solvers)
+}
+
+/// Solve every puzzle with each solver in turn, recording how long each one
+/// took. Puzzles that fail to parse are reported and skipped.
+fn run<I>(mut puzzles: I, cli: &Cli, solvers: &[Box<dyn Solver>]) -> Vec<Record>
+where
+    I: Iterator<Item = Result<Puzzle, String>>,
+{
+    let mut records = Vec::new();
+    let mut index = 0;
+
+    loop {
+        let parse_start = Instant::now();
+        let Some(result) = puzzles.next() else {
+            break;
+        };
+        index += 1;
+
+        let puzzle = match result {
+            Ok(puzzle) => puzzle,
+            Err(err) => {
+                eprintln!("{}", err);
+                continue;
+            }
+        };
+        let parse_time = parse_start.elapsed();
+        let clues = puzzle.clue_count();
+
+        for solver in solvers.iter() {
+            let mut grid = puzzle;
+            let mut stats = SolveStats::default();
+
+            let solve_start = Instant::now();
+            let result = if cli.strict {
+                solver.solve_strict_with_stats(&mut grid, &mut stats)
+            } else {
+                solver.solve_with_stats(&mut grid, &mut stats)
+            };
+            let mut elapsed = solve_start.elapsed();
+            if cli.include_parse {
+                elapsed += parse_time;
+            }
+
+            if let Err(err) = &result {
+                eprintln!("{} puzzle {}: {:?}", solver.name(), index, err);
+            }
+
+            records.push(Record::new(
+                solver.name(),
+                index,
+                clues,
+                elapsed,
+                &result,
+                stats,
+                solver.tracks_search_stats(),
+            ));
+        }
+    }
+
+    records
+}
+
+/// Print each logical step that removes possible numbers from the puzzle,
+/// propagating after every step, until none is left.
+fn explain(puzzle: &Puzzle, options: &logic::Options) {
+    let explanation = logic::explain(puzzle, options);
+    for step in &explanation.steps {
+        println!("{}", step);
+    }
+    println!("After logical steps:\n{}", explanation.grid.pretty_print());
+    println!("Grade: {}", explanation.grade);
+}
+
+fn demo(cli: &Cli, solver: &dyn Solver) {
+    let mut puzzle = match cli.puzzle.as_deref() {
+        Some(line) => match Puzzle::try_from_line_str(line) {
+            Ok(puzzle) => puzzle,
+            Err(err) => {
+                eprintln!("Invalid puzzle: {}", err);
+                process::exit(2);
+            }
+        },
+        None => match parse_csv(puzzles::OK, cli.lenient) {
+            Ok(puzzle) => puzzle,
+            Err(err) => panic!("Invalid puzzle: {}", err),
+        },
+    };
     println!("Puzzle:\n{}", puzzle.pretty_print());
+    println!("{}", puzzle.format_line());
 
-    if let Ok(()) = puzzle.solve() {
-        println!("Solved puzzle:\n{}", puzzle.pretty_print());
+    println!("Unique solution: {}", solver.is_unique(&puzzle));
+
+    if cli.explain {
+        let options = logic::Options {
+            max_chain_length: cli.max_chain_length,
+            assume_unique: cli.assume_unique,
+        };
+        explain(&puzzle, &options);
+    }
+
+    if let Some(limit) = cli.solutions {
+        for (i, solution) in puzzle.solutions().take(limit).enumerate() {
+            println!("Solution {}: {}", i + 1, solution.format_line());
+        }
+        return;
+    }
+
+    let result = if cli.strict {
+        solver.solve_strict(&mut puzzle)
+    } else {
+        solver.solve(&mut puzzle)
     };
+
+    match result {
+        Ok(()) => {
+            println!("Solved puzzle:\n{}", puzzle.pretty_print());
+            println!("{}", puzzle.format_line());
+        }
+        Err(err) => println!("Could not solve puzzle: {:?}", err),
+    }
 }
@@ -24,7 +24,7 @@ impl Number {
         Self::Nine,
     ];
 
-    pub fn to_u8(&self) -> u8 {
+    pub fn to_u8(self) -> u8 {
         match self {
             Self::One => 1,
             Self::Two => 2,
@@ -75,7 +75,7 @@ impl Number {
         }
     }
 
-    pub fn to_char(self: &Self) -> char {
+    pub fn to_char(self) -> char {
         match self {
             Self::One => '1',
             Self::Two => '2',
@@ -91,11 +91,5 @@ impl Number {
 }
 
 fn normalize(int: u8) -> u8 {
-    if int < 1 {
-        1
-    } else if int > 9 {
-        9
-    } else {
-        int
-    }
+    int.clamp(1, 9)
 }
@@ -10,10 +10,44 @@ impl PossibleNums {
     /// `PossibleNums` with all numbers possible
     pub const ALL: PossibleNums = PossibleNums { bits: 0b111111111 };
 
+    /// `PossibleNums` with no numbers possible
+    pub const NONE: PossibleNums = PossibleNums { bits: 0 };
+
+    pub fn insert(&mut self, num: Number) {
+        self.bits |= 1 << (num.to_u8() - 1);
+    }
+
     pub fn remove(&mut self, num: Number) {
         self.bits &= !(1 << (num.to_u8() - 1));
     }
 
+    pub fn contains(&self, num: Number) -> bool {
+        self.bits & (1 << (num.to_u8() - 1)) != 0
+    }
+
+    /// The numbers possible in either set.
+    pub fn union(self, other: PossibleNums) -> PossibleNums {
+        PossibleNums {
+            bits: self.bits | other.bits,
+        }
+    }
+
+    /// The numbers possible in both sets.
+    pub fn intersection(self, other: PossibleNums) -> PossibleNums {
+        PossibleNums {
+            bits: self.bits & other.bits,
+        }
+    }
+
+    /// The number of possible numbers.
+    pub fn len(&self) -> u32 {
+        self.bits.count_ones()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bits == 0
+    }
+
     pub fn single(&self) -> Option<Number> {
         if self.bits.count_ones() == 1 {
             let mut bits = self.bits;
@@ -96,6 +130,40 @@ mod tests {
         assert_eq!(possible_nums.bits, 0b011111110);
     }
 
+    #[test]
+    fn contains() {
+        let possible_nums = PossibleNums { bits: 0b100000010 };
+        assert!(possible_nums.contains(Number::Two));
+        assert!(possible_nums.contains(Number::Nine));
+        assert!(!possible_nums.contains(Number::One));
+    }
+
+    #[test]
+    fn set_operations() {
+        let mut a = PossibleNums::NONE;
+        a.insert(Number::One);
+        a.insert(Number::Five);
+        let b = PossibleNums { bits: 0b000011000 };
+
+        assert_eq!(a.bits, 0b000010001);
+        assert_eq!(a.union(b).bits, 0b000011001);
+        assert_eq!(a.intersection(b).bits, 0b000010000);
+        assert!(a.intersection(PossibleNums::NONE).is_empty());
+    }
+
+    #[test]
+    fn len() {
+        assert_eq!(PossibleNums::ALL.len(), 9);
+        assert!(!PossibleNums::ALL.is_empty());
+
+        let possible_nums = PossibleNums { bits: 0b001000010 };
+        assert_eq!(possible_nums.len(), 2);
+
+        let possible_nums = PossibleNums { bits: 0 };
+        assert_eq!(possible_nums.len(), 0);
+        assert!(possible_nums.is_empty());
+    }
+
     #[test]
     fn single() {
         let possible_nums = PossibleNums { bits: 0b000000010 };
@@ -1,5 +1,5 @@
 /// A valid, solvable puzzle.
-pub const OK: &'static str = concat!(
+pub const OK: &str = concat!(
     "0,0,0,0,0,2,1,0,4\n",
     "0,0,8,0,0,1,0,0,3\n",
     "5,0,0,0,6,0,0,9,0\n",
@@ -0,0 +1,552 @@
+use crate::smart_backtrack::{SolveError, SolveStats};
+use crate::stats::Summary;
+use clap::ValueEnum;
+use std::io::{self, Write};
+use std::process::Command;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Output format for benchmark results.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// Human-readable summary
+    Text,
+    /// One JSON object per line: a record per puzzle, then a summary
+    Json,
+    /// A table of per-puzzle records, a blank line, then a summary table
+    Csv,
+}
+
+/// The outcome of solving a single puzzle.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    pub solver: String,
+    /// Position of the puzzle in the input, numbered from 1.
+    pub index: usize,
+    pub clues: usize,
+    pub time: Duration,
+    pub result: String,
+    pub stats: SolveStats,
+    /// Whether the solver tracked every field of `stats`, rather than only
+    /// `nodes`. The other fields are written as `null` when it didn't.
+    pub search_stats: bool,
+}
+impl Record {
+    pub fn new(
+        solver: &str,
+        index: usize,
+        clues: usize,
+        time: Duration,
+        result: &Result<(), SolveError>,
+        stats: SolveStats,
+        search_stats: bool,
+    ) -> Self {
+        let result = match result {
+            Ok(()) => "solved",
+            Err(SolveError::TooFewHints) => "too_few_hints",
+            Err(SolveError::IllegalPuzzle) => "illegal_puzzle",
+            Err(SolveError::NoSolutionFound) => "no_solution",
+            Err(SolveError::MultipleSolutions) => "multiple_solutions",
+        };
+
+        Self {
+            solver: solver.to_string(),
+            index,
+            clues,
+            time,
+            result: result.to_string(),
+            stats,
+            search_stats,
+        }
+    }
+
+    pub fn is_solved(&self) -> bool {
+        self.result == "solved"
+    }
+
+    /// The per-puzzle fields shared by the JSON and CSV formats, in the
+    /// order of `RECORD_FIELDS`.
+    fn fields(&self) -> [Value; 12] {
+        let number = |value: &dyn ToString| Value::Number(value.to_string());
+        let search = |value: &dyn ToString| {
+            if self.search_stats {
+                number(value)
+            } else {
+                Value::Null
+            }
+        };
+        let stats = &self.stats;
+
+        [
+            Value::String(Some(self.solver.clone())),
+            number(&self.index),
+            number(&self.clues),
+            number(&self.time.as_nanos()),
+            Value::String(Some(self.result.clone())),
+            number(&stats.nodes),
+            search(&stats.backtracks),
+            search(&stats.max_depth),
+            search(&stats.prune_calls),
+            search(&stats.prune_parents_calls),
+            search(&stats.propagated),
+            search(&stats.guessed),
+        ]
+    }
+}
+
+/// Names of the per-puzzle fields written by the JSON and CSV formats.
+pub const RECORD_FIELDS: [&str; 12] = [
+    "solver",
+    "index",
+    "clues",
+    "time_ns",
+    "result",
+    "nodes",
+    "backtracks",
+    "max_depth",
+    "prune_calls",
+    "prune_parents_calls",
+    "propagated",
+    "guessed",
+];
+
+/// Information about the environment a benchmark was run in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Metadata {
+    pub input: String,
+    pub git_revision: Option<String>,
+    pub profile: &'static str,
+    pub cpus: usize,
+    /// Seconds since the Unix epoch.
+    pub timestamp: u64,
+}
+impl Metadata {
+    pub fn collect(input: String) -> Self {
+        let git_revision = Command::new("git")
+            .args(["rev-parse", "--short", "HEAD"])
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .and_then(|output| String::from_utf8(output.stdout).ok())
+            .map(|revision| revision.trim().to_string());
+
+        Self {
+            input,
+            git_revision,
+            profile: if cfg!(debug_assertions) {
+                "debug"
+            } else {
+                "release"
+            },
+            cpus: thread::available_parallelism().map_or(1, |n| n.get()),
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |time| time.as_secs()),
+        }
+    }
+}
+
+/// The results of one solver over the benchmarked puzzles.
+pub struct SolverSummary {
+    pub solver: String,
+    pub puzzles: usize,
+    pub failed: usize,
+    /// Times of the solved puzzles, or `None` if none were solved.
+    pub summary: Option<Summary>,
+}
+
+/// The results of a benchmark run.
+pub struct Report {
+    pub records: Vec<Record>,
+    /// One summary per solver, in the order they first appear in `records`.
+    pub solvers: Vec<SolverSummary>,
+    pub metadata: Metadata,
+}
+impl Report {
+    /// Build a report, summarizing the times of each solver's solved puzzles.
+    pub fn new(records: Vec<Record>, metadata: Metadata) -> Self {
+        let mut names: Vec<&str> = Vec::new();
+        for record in records.iter() {
+            if !names.contains(&record.solver.as_str()) {
+                names.push(&record.solver);
+            }
+        }
+
+        let solvers = names
+            .into_iter()
+            .map(|solver| {
+                let records: Vec<&Record> = records.iter().filter(|r| r.solver == solver).collect();
+                let times: Vec<Duration> = records
+                    .iter()
+                    .filter(|record| record.is_solved())
+                    .map(|record| record.time)
+                    .collect();
+
+                SolverSummary {
+                    solver: solver.to_string(),
+                    puzzles: records.len(),
+                    failed: records.len() - times.len(),
+                    summary: Summary::from_durations(&times),
+                }
+            })
+            .collect();
+
+        Self {
+            records,
+            solvers,
+            metadata,
+        }
+    }
+
+    pub fn write(&self, format: Format, out: &mut impl Write) -> io::Result<()> {
+        match format {
+            Format::Text => self.write_text(out),
+            Format::Json => self.write_json(out),
+            Format::Csv => self.write_csv(out),
+        }
+    }
+
+    fn write_text(&self, out: &mut impl Write) -> io::Result<()> {
+        match self.solvers.as_slice() {
+            [] => writeln!(out, "No puzzles were solved"),
+            [solver] => {
+                match &solver.summary {
+                    Some(summary) => writeln!(out, "{}", summary)?,
+                    None => writeln!(out, "No puzzles were solved")?,
+                }
+                if solver.failed > 0 {
+                    writeln!(out, "failed:     {}", solver.failed)?;
+                }
+                Ok(())
+            }
+            solvers => write_table(solvers, out),
+        }
+    }
+
+    fn write_json(&self, out: &mut impl Write) -> io::Result<()> {
+        for record in self.records.iter() {
+            let fields = RECORD_FIELDS.into_iter().zip(record.fields());
+            writeln!(out, "{{\"type\":\"puzzle\",{}}}", json_fields(fields))?;
+        }
+
+        for solver in self.solvers.iter() {
+            let fields = self.summary_fields(solver);
+            writeln!(out, "{{\"type\":\"summary\",{}}}", json_fields(fields))?;
+        }
+
+        Ok(())
+    }
+
+    fn write_csv(&self, out: &mut impl Write) -> io::Result<()> {
+        writeln!(out, "{}", RECORD_FIELDS.join(","))?;
+        for record in self.records.iter() {
+            let values: Vec<String> = record.fields().into_iter().map(Value::into_csv).collect();
+            writeln!(out, "{}", values.join(","))?;
+        }
+
+        for (i, solver) in self.solvers.iter().enumerate() {
+            let (keys, values): (Vec<&str>, Vec<String>) = self
+                .summary_fields(solver)
+                .into_iter()
+                .map(|(key, value)| (key, value.into_csv()))
+                .unzip();
+
+            if i == 0 {
+                writeln!(out)?;
+                writeln!(out, "{}", keys.join(","))?;
+            }
+            writeln!(out, "{}", values.join(","))?;
+        }
+
+        Ok(())
+    }
+
+    /// The summary record shared by the JSON and CSV formats.
+    fn summary_fields(&self, solver: &SolverSummary) -> Vec<(&'static str, Value)> {
+        let ns = |select: fn(&Summary) -> Duration| {
+            Value::Number(
+                solver
+                    .summary
+                    .as_ref()
+                    .map_or(String::from("0"), |s| select(s).as_nanos().to_string()),
+            )
+        };
+        let metadata = &self.metadata;
+
+        vec![
+            ("solver", Value::String(Some(solver.solver.clone()))),
+            ("puzzles", Value::Number(solver.puzzles.to_string())),
+            (
+                "solved",
+                Value::Number((solver.puzzles - solver.failed).to_string()),
+            ),
+            ("failed", Value::Number(solver.failed.to_string())),
+            ("total_ns", ns(|s| s.total)),
+            ("mean_ns", ns(|s| s.mean)),
+            ("median_ns", ns(|s| s.median)),
+            ("p90_ns", ns(|s| s.p90)),
+            ("p99_ns", ns(|s| s.p99)),
+            ("min_ns", ns(|s| s.min)),
+            ("max_ns", ns(|s| s.max)),
+            (
+                "puzzles_per_second",
+                Value::Number(
+                    solver
+                        .summary
+                        .as_ref()
+                        .map_or(String::from("0"), |s| format!("{:.3}", s.per_second())),
+                ),
+            ),
+            ("input", Value::String(Some(metadata.input.clone()))),
+            ("git_revision", Value::String(metadata.git_revision.clone())),
+            ("profile", Value::String(Some(metadata.profile.to_string()))),
+            ("cpus", Value::Number(metadata.cpus.to_string())),
+            ("timestamp", Value::Number(metadata.timestamp.to_string())),
+        ]
+    }
+}
+
+/// Write the summaries of several solvers side by side, one column each.
+fn write_table(solvers: &[SolverSummary], out: &mut impl Write) -> io::Result<()> {
+    let row = |label: &'static str, value: &dyn Fn(&SolverSummary) -> String| {
+        (label, solvers.iter().map(value).collect::<Vec<String>>())
+    };
+    let time = |label, select: fn(&Summary) -> Duration| {
+        row(label, &|solver| {
+            solver
+                .summary
+                .as_ref()
+                .map_or(String::from("-"), |s| format!("{:.3?}", select(s)))
+        })
+    };
+
+    let rows = [
+        row("", &|s| s.solver.clone()),
+        row("solved:", &|s| (s.puzzles - s.failed).to_string()),
+        row("failed:", &|s| s.failed.to_string()),
+        time("total:", |s| s.total),
+        time("mean:", |s| s.mean),
+        time("median:", |s| s.median),
+        time("p90:", |s| s.p90),
+        time("p99:", |s| s.p99),
+        time("min:", |s| s.min),
+        time("max:", |s| s.max),
+        row("puzzles/s:", &|s| {
+            s.summary
+                .as_ref()
+                .map_or(String::from("-"), |s| format!("{:.1}", s.per_second()))
+        }),
+    ];
+
+    let widths: Vec<usize> = (0..solvers.len())
+        .map(|column| {
+            rows.iter()
+                .map(|(_, cells)| cells[column].chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    for (label, cells) in rows.iter() {
+        let mut line = format!("{:12}", label);
+        for (cell, width) in cells.iter().zip(widths.iter()) {
+            line.push_str(&format!("{:>width$}  ", cell, width = width));
+        }
+        writeln!(out, "{}", line.trim_end())?;
+    }
+
+    Ok(())
+}
+
+enum Value {
+    Number(String),
+    String(Option<String>),
+    /// A number that wasn't measured.
+    Null,
+}
+impl Value {
+    fn into_json(self) -> String {
+        match self {
+            Value::Number(number) => number,
+            Value::String(Some(string)) => json_string(&string),
+            Value::String(None) | Value::Null => String::from("null"),
+        }
+    }
+
+    fn into_csv(self) -> String {
+        match self {
+            Value::Number(number) => number,
+            Value::String(string) => csv_field(string.as_deref().unwrap_or("")),
+            Value::Null => String::new(),
+        }
+    }
+}
+
+/// Format key-value pairs as the members of a JSON object.
+fn json_fields(fields: impl IntoIterator<Item = (&'static str, Value)>) -> String {
+    let members: Vec<String> = fields
+        .into_iter()
+        .map(|(key, value)| format!("{}:{}", json_string(key), value.into_json()))
+        .collect();
+    members.join(",")
+}
+
+fn json_string(string: &str) -> String {
+    let mut output = String::with_capacity(string.len() + 2);
+    output.push('"');
+
+    for char in string.chars() {
+        match char {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            char if (char as u32) < 0x20 => output.push_str(&format!("\\u{:04x}", char as u32)),
+            char => output.push(char),
+        }
+    }
+
+    output.push('"');
+    output
+}
+
+fn csv_field(string: &str) -> String {
+    if string.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", string.replace('"', "\"\""))
+    } else {
+        string.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata() -> Metadata {
+        Metadata {
+            input: String::from("corpus, \"hard\".txt"),
+            git_revision: None,
+            profile: "release",
+            cpus: 4,
+            timestamp: 1700000000,
+        }
+    }
+
+    fn stats() -> SolveStats {
+        SolveStats {
+            nodes: 12,
+            backtracks: 3,
+            max_depth: 4,
+            prune_calls: 2,
+            prune_parents_calls: 12,
+            propagated: 54,
+            guessed: 3,
+        }
+    }
+
+    fn report() -> Report {
+        let records = vec![
+            Record::new(
+                "dlx",
+                1,
+                24,
+                Duration::from_micros(30),
+                &Ok(()),
+                stats(),
+                true,
+            ),
+            Record::new(
+                "dlx",
+                2,
+                3,
+                Duration::from_micros(1),
+                &Err(SolveError::TooFewHints),
+                SolveStats::default(),
+                false,
+            ),
+        ];
+
+        Report::new(records, metadata())
+    }
+
+    fn write(format: Format) -> String {
+        let mut out = Vec::new();
+        report().write(format, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_json() {
+        let output = write(Format::Json);
+        let lines: Vec<&str> = output.lines().collect();
+
+        assert_eq!(lines.len(), 3);
+        assert_eq!(
+            lines[0],
+            "{\"type\":\"puzzle\",\"solver\":\"dlx\",\"index\":1,\"clues\":24,\
+             \"time_ns\":30000,\"result\":\"solved\",\"nodes\":12,\"backtracks\":3,\
+             \"max_depth\":4,\"prune_calls\":2,\"prune_parents_calls\":12,\
+             \"propagated\":54,\"guessed\":3}"
+        );
+        assert!(lines[1].contains("\"result\":\"too_few_hints\",\"nodes\":0,\"backtracks\":null,"));
+        assert!(lines[2].starts_with("{\"type\":\"summary\",\"solver\":\"dlx\",\"puzzles\":2,"));
+        assert!(lines[2].contains("\"median_ns\":30000,"));
+        assert!(lines[2].contains("\"input\":\"corpus, \\\"hard\\\".txt\","));
+        assert!(lines[2].contains("\"git_revision\":null,"));
+    }
+
+    #[test]
+    fn test_csv() {
+        let output = write(Format::Csv);
+        let lines: Vec<&str> = output.lines().collect();
+
+        assert_eq!(
+            lines[0],
+            "solver,index,clues,time_ns,result,nodes,backtracks,max_depth,\
+             prune_calls,prune_parents_calls,propagated,guessed"
+        );
+        assert_eq!(lines[1], "dlx,1,24,30000,solved,12,3,4,2,12,54,3");
+        assert_eq!(lines[2], "dlx,2,3,1000,too_few_hints,0,,,,,,");
+        assert_eq!(lines[3], "");
+        assert!(lines[4].starts_with("solver,puzzles,solved,failed,total_ns,"));
+        assert!(lines[5].starts_with("dlx,2,1,1,30000,"));
+        assert!(lines[5].contains(",\"corpus, \"\"hard\"\".txt\",,release,4,"));
+    }
+
+    #[test]
+    fn test_several_solvers() {
+        let mut records = Vec::new();
+        for index in 1..=2 {
+            for (solver, us) in [("smart-backtrack", 40), ("bitmask", 10)] {
+                let time = Duration::from_micros(us);
+                records.push(Record::new(solver, index, 24, time, &Ok(()), stats(), true));
+            }
+        }
+        let report = Report::new(records, metadata());
+
+        let solvers: Vec<&str> = report.solvers.iter().map(|s| s.solver.as_str()).collect();
+        assert_eq!(solvers, ["smart-backtrack", "bitmask"]);
+        assert_eq!(report.solvers[1].puzzles, 2);
+        assert_eq!(
+            report.solvers[1].summary.unwrap().total,
+            Duration::from_micros(20)
+        );
+
+        let mut out = Vec::new();
+        report.write(Format::Text, &mut out).unwrap();
+        let output = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[0], "            smart-backtrack   bitmask");
+        assert_eq!(lines[1], "solved:                   2         2");
+        assert_eq!(lines[3], "total:             80.000µs  20.000µs");
+
+        let mut out = Vec::new();
+        report.write(Format::Csv, &mut out).unwrap();
+        let output = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 9);
+        assert!(lines[7].starts_with("smart-backtrack,2,2,0,80000,"));
+        assert!(lines[8].starts_with("bitmask,2,2,0,20000,"));
+    }
+}
@@ -1,6 +1,9 @@
+use crate::logic;
 use crate::number::Number;
-use crate::possible_nums::PossibleNums;
+use crate::possible_nums::{PossibleNums, PossibleNumsIterator};
+use clap::ValueEnum;
 use std::array;
+use std::fmt;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum Cell {
@@ -15,7 +18,7 @@ impl Cell {
         }
     }
 
-    pub fn to_char(self: &Self) -> char {
+    pub fn to_char(self) -> char {
         match self {
             Cell::Fixed(num) => num.to_char(),
             Cell::Empty(_) => ' ',
@@ -59,7 +62,16 @@ impl Coord {
         (row / 3) * 3 + (col / 3)
     }
 
-    pub fn next(self: &Self) -> Option<Self> {
+    /// Whether the other cell is a different cell in the same row, column
+    /// or box.
+    pub fn sees(&self, other: &Coord) -> bool {
+        self != other
+            && (self.parent_row() == other.parent_row()
+                || self.parent_col() == other.parent_col()
+                || self.parent_box() == other.parent_box())
+    }
+
+    pub fn next(&self) -> Option<Self> {
         if self.index < 80 {
             Some(Self {
                 index: self.index + 1,
@@ -70,14 +82,75 @@ impl Coord {
     }
 }
 
+impl fmt::Display for Coord {
+    /// Formats the cell as `r<row>c<col>`, numbered from 1.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "r{}c{}", self.parent_row() + 1, self.parent_col() + 1)
+    }
+}
+
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub struct Grid {
     cells: [Cell; 81],
 }
 impl Grid {
-    pub fn from_csv_str(input: &str) -> Self {
+    /// Parse a grid from 9 lines of 9 comma-separated values, where `0` or
+    /// an empty value marks an empty cell.
+    ///
+    /// Any malformed input is rejected with the position of the problem.
+    pub fn try_from_csv_str(input: &str) -> Result<Self, ParseError> {
+        let mut lines: Vec<&str> = input.lines().collect();
+
+        while lines.last().is_some_and(|line| line.trim().is_empty()) {
+            lines.pop();
+        }
+
+        if lines.len() != 9 {
+            return Err(ParseError::WrongRowCount { count: lines.len() });
+        }
+
+        let mut output: Self = Self {
+            cells: array::from_fn(|_| Cell::init_empty()),
+        };
+
+        for (row, line) in lines.into_iter().enumerate() {
+            let tokens: Vec<&str> = line.split(',').map(str::trim).collect();
+
+            if tokens.len() != 9 {
+                return Err(ParseError::WrongRowLength {
+                    row,
+                    len: tokens.len(),
+                });
+            }
+
+            for (col, token) in tokens.into_iter().enumerate() {
+                let cell = parse_csv_token(token).map_err(|kind| match kind {
+                    TokenError::OutOfRange => ParseError::ValueOutOfRange {
+                        row,
+                        col,
+                        token: token.to_string(),
+                    },
+                    TokenError::Invalid => ParseError::InvalidToken {
+                        row,
+                        col,
+                        token: token.to_string(),
+                    },
+                })?;
+                output.cells[row * 9 + col] = cell;
+            }
+        }
+
+        Ok(output)
+    }
+
+    /// Parse a grid from comma-separated values without validating it.
+    ///
+    /// Short input is padded with empty cells, anything after the 81st value
+    /// is ignored, and any value that is not a digit from 1 to 9 becomes an
+    /// empty cell. Prefer `try_from_csv_str` unless this is really wanted.
+    pub fn from_csv_str_lenient(input: &str) -> Self {
         let cells = input
-            .split(|c| c == ',' || c == '\n')
+            .split([',', '\n'])
             .map(|str| match str.chars().next() {
                 Some(char) => Cell::from_char(char),
                 None => Cell::init_empty(),
@@ -95,7 +168,52 @@ impl Grid {
             }
         }
 
-        return output;
+        output
+    }
+
+    /// Parse a grid from a single line of 81 characters, read row by row.
+    /// Digits 1 to 9 are clues, and `.`, `0`, `_` or `*` mark empty cells.
+    pub fn try_from_line_str(input: &str) -> Result<Self, ParseError> {
+        let line = input.trim();
+        let len = line.chars().count();
+
+        if len != 81 {
+            return Err(ParseError::WrongLineLength { len });
+        }
+
+        let mut output: Self = Self {
+            cells: array::from_fn(|_| Cell::init_empty()),
+        };
+
+        for (i, char) in line.chars().enumerate() {
+            output.cells[i] = match char {
+                '.' | '0' | '_' | '*' => Cell::init_empty(),
+                _ => match Number::from_char(char) {
+                    Some(num) => Cell::Fixed(num),
+                    None => {
+                        return Err(ParseError::InvalidToken {
+                            row: i / 9,
+                            col: i % 9,
+                            token: char.to_string(),
+                        })
+                    }
+                },
+            };
+        }
+
+        Ok(output)
+    }
+
+    /// Serialize the grid as a single line of 81 characters, using `.` for
+    /// empty cells.
+    pub fn format_line(&self) -> String {
+        self.cells
+            .iter()
+            .map(|cell| match cell {
+                Cell::Fixed(num) => num.to_char(),
+                Cell::Empty(_) => '.',
+            })
+            .collect()
     }
 
     pub fn pretty_print(&self) -> String {
@@ -183,37 +301,75 @@ impl Grid {
         array::from_fn(|i| self.get(coords[i]))
     }
 
-    pub fn rows(&self) -> RowIterator {
-        RowIterator {
-            grid: &self,
-            row: 0,
-        }
+    pub fn rows(&self) -> RowIterator<'_> {
+        RowIterator { grid: self, row: 0 }
     }
 
-    pub fn cols(&self) -> ColIterator {
-        ColIterator {
-            grid: &self,
-            col: 0,
-        }
+    pub fn cols(&self) -> ColIterator<'_> {
+        ColIterator { grid: self, col: 0 }
     }
 
-    pub fn boxes(&self) -> BoxIterator {
+    pub fn boxes(&self) -> BoxIterator<'_> {
         BoxIterator {
-            grid: &self,
+            grid: self,
             box_: 0,
         }
     }
 
+    #[allow(dead_code)]
     pub fn solve(&mut self) -> Result<(), SolveError> {
+        self.solve_counting_nodes(&mut 0)
+    }
+
+    /// Solve the puzzle, adding the number of search nodes visited (numbers
+    /// tentatively placed in a cell) to `nodes`.
+    #[allow(dead_code)]
+    pub fn solve_counting_nodes(&mut self, nodes: &mut u64) -> Result<(), SolveError> {
+        self.solve_with_branching(Branching::Linear, nodes)
+    }
+
+    /// Solve the puzzle, choosing which empty cell to branch on next with
+    /// `branching`, and adding the number of search nodes visited to
+    /// `nodes`.
+    #[allow(dead_code)]
+    pub fn solve_with_branching(
+        &mut self,
+        branching: Branching,
+        nodes: &mut u64,
+    ) -> Result<(), SolveError> {
+        let mut stats = SolveStats::default();
+        let result = self.solve_with_stats(branching, Propagation::default(), &mut stats);
+        *nodes += stats.nodes;
+        result
+    }
+
+    /// Like `solve_with_branching`, propagating with `propagation` and
+    /// adding everything the search did to `stats`.
+    pub fn solve_with_stats(
+        &mut self,
+        branching: Branching,
+        propagation: Propagation,
+        stats: &mut SolveStats,
+    ) -> Result<(), SolveError> {
         if !self.is_legal() {
-            return Err(SolveError::IllegalPuzzle);
+            Err(SolveError::IllegalPuzzle)
         } else if !self.has_sufficient_hints() {
-            return Err(SolveError::TooFewHints);
+            Err(SolveError::TooFewHints)
         } else {
-            self.prune();
-            match solve_helper(self, Coord::FIRST) {
+            let empty = 81 - self.clue_count();
+            let guessed = stats.guessed;
+
+            if self.prune_with_stats(propagation, stats).is_err() {
+                return Err(SolveError::NoSolutionFound);
+            }
+            let solved = match branching {
+                Branching::Linear => solve_helper(self, Coord::FIRST, 0, propagation, stats),
+                Branching::MinRemaining => solve_helper_mrv(self, 0, propagation, stats),
+            };
+            match solved {
                 Ok(solved) => {
                     *self = solved;
+                    stats.propagated += empty - (stats.guessed - guessed);
                     Ok(())
                 }
                 Err(err) => Err(err),
@@ -221,101 +377,227 @@ impl Grid {
         }
     }
 
-    pub fn prune(&mut self) {
-        let old_grid = self.clone();
-        let mut coords: [Coord; 9];
-        let mut house: [Cell; 9] = array::from_fn(|_| Cell::init_empty());
+    /// Solve the puzzle, failing with `SolveError::MultipleSolutions` if it
+    /// does not have exactly one solution.
+    #[allow(dead_code)]
+    pub fn solve_strict(&mut self) -> Result<(), SolveError> {
+        self.solve_strict_counting_nodes(&mut 0)
+    }
 
-        for i in 0..9 {
-            // Prune rows
-            coords = row_coords(i as u8);
-            for (j, coord) in coords.into_iter().enumerate() {
-                house[j] = self.get(coord);
-            }
-            prune_house(&mut house);
-            for j in 0..9 {
-                self.set(coords[j], house[j]);
-            }
+    /// Like `solve_strict`, adding the number of search nodes visited to
+    /// `nodes`.
+    #[allow(dead_code)]
+    pub fn solve_strict_counting_nodes(&mut self, nodes: &mut u64) -> Result<(), SolveError> {
+        let mut stats = SolveStats::default();
+        let result =
+            self.solve_strict_with_stats(Branching::Linear, Propagation::default(), &mut stats);
+        *nodes += stats.nodes;
+        result
+    }
 
-            // Prune Cols
-            coords = col_coords(i as u8);
-            for (j, coord) in coords.into_iter().enumerate() {
-                house[j] = self.get(coord);
-            }
-            prune_house(&mut house);
-            for j in 0..9 {
-                self.set(coords[j], house[j]);
-            }
+    /// Like `solve_strict`, branching with `branching`, propagating with
+    /// `propagation` and adding everything the search for a first and second
+    /// solution did to `stats`. Cells filled by propagation and by guessing
+    /// are not tracked.
+    pub fn solve_strict_with_stats(
+        &mut self,
+        branching: Branching,
+        propagation: Propagation,
+        stats: &mut SolveStats,
+    ) -> Result<(), SolveError> {
+        if !self.is_legal() {
+            Err(SolveError::IllegalPuzzle)
+        } else if !self.has_sufficient_hints() {
+            Err(SolveError::TooFewHints)
+        } else {
+            let mut solutions = self.solutions_with(branching, propagation);
+            let first = solutions.next();
+            let second = solutions.next();
+            stats.add(solutions.stats());
 
-            // Prune Boxes
-            coords = box_coords(i as u8);
-            for (j, coord) in coords.into_iter().enumerate() {
-                house[j] = self.get(coord);
-            }
-            prune_house(&mut house);
-            for j in 0..9 {
-                self.set(coords[j], house[j]);
+            match (first, second) {
+                (Some(solved), None) => {
+                    *self = solved;
+                    Ok(())
+                }
+                (None, _) => Err(SolveError::NoSolutionFound),
+                (Some(_), Some(_)) => Err(SolveError::MultipleSolutions),
             }
         }
+    }
 
-        if *self != old_grid {
-            self.prune();
-        }
+    /// Iterate over every solution of the puzzle, without checking that it
+    /// has enough hints.
+    ///
+    /// Solutions are found lazily, using an explicit stack rather than
+    /// recursion, so grids with very few clues can be explored safely.
+    pub fn solutions(&self) -> Solutions {
+        self.solutions_with(Branching::Linear, Propagation::default())
     }
 
-    fn prune_parents(&mut self, coord: &Coord) {
-        let mut house: [Cell; 9] = array::from_fn(|_| Cell::init_empty());
-        let mut coords = row_coords(coord.parent_row());
+    /// Like `solutions`, choosing which empty cell to branch on next with
+    /// `branching` and propagating with `propagation`.
+    pub fn solutions_with(&self, branching: Branching, propagation: Propagation) -> Solutions {
+        let mut solutions = Solutions {
+            stack: Vec::new(),
+            pending: None,
+            branching,
+            propagation,
+            stats: SolveStats::default(),
+        };
 
-        // Prune row
-        for (j, coord) in coords.into_iter().enumerate() {
-            house[j] = self.get(coord);
-        }
-        prune_house(&mut house);
-        for j in 0..9 {
-            self.set(coords[j], house[j]);
+        let mut grid = *self;
+        if self.is_legal()
+            && grid
+                .prune_with_stats(propagation, &mut solutions.stats)
+                .is_ok()
+        {
+            solutions.descend(grid, Coord::FIRST);
         }
 
-        // Prune Col
-        coords = col_coords(coord.parent_col());
-        for (j, coord) in coords.into_iter().enumerate() {
-            house[j] = self.get(coord);
+        solutions
+    }
+
+    /// Count the solutions of the puzzle, stopping once `limit` have been
+    /// found.
+    #[allow(dead_code)]
+    pub fn count_solutions(&self, limit: usize) -> usize {
+        self.solutions().take(limit).count()
+    }
+
+    /// Whether the puzzle has exactly one solution.
+    #[allow(dead_code)]
+    pub fn is_unique(&self) -> bool {
+        self.count_solutions(2) == 1
+    }
+
+    /// Remove fixed numbers from the possible numbers of the empty cells in
+    /// their row, column and box, fixing cells left with a single number,
+    /// until nothing changes. Fails as soon as a cell has no possible
+    /// numbers left or a number is fixed twice in a house.
+    #[allow(dead_code)]
+    pub fn prune(&mut self) -> Result<(), Contradiction> {
+        self.prune_with(Propagation::default())
+    }
+
+    /// Like `prune`, also making the deductions enabled in `propagation`.
+    pub fn prune_with(&mut self, propagation: Propagation) -> Result<(), Contradiction> {
+        self.prune_with_stats(propagation, &mut SolveStats::default())
+    }
+
+    /// Like `prune_with`, counting each pass over the grid in `stats`.
+    fn prune_with_stats(
+        &mut self,
+        propagation: Propagation,
+        stats: &mut SolveStats,
+    ) -> Result<(), Contradiction> {
+        stats.prune_calls += 1;
+        let old_grid = *self;
+        let mut fixed = Vec::new();
+
+        for i in 0..9 {
+            self.prune_coords(row_coords(i), propagation, &mut fixed)?;
+            self.prune_coords(col_coords(i), propagation, &mut fixed)?;
+            self.prune_coords(box_coords(i), propagation, &mut fixed)?;
         }
-        prune_house(&mut house);
-        for j in 0..9 {
-            self.set(coords[j], house[j]);
+        if propagation.intersections {
+            for step in logic::intersections(self) {
+                step.apply(self);
+            }
         }
 
-        // Prune Box
-        coords = box_coords(coord.parent_box());
-        for (j, coord) in coords.into_iter().enumerate() {
-            house[j] = self.get(coord);
+        if *self != old_grid {
+            self.prune_with_stats(propagation, stats)
+        } else {
+            Ok(())
         }
-        prune_house(&mut house);
-        for j in 0..9 {
-            self.set(coords[j], house[j]);
+    }
+
+    /// Prune the row, column and box containing `coord`, and those of every
+    /// cell that becomes fixed as a result.
+    pub(crate) fn prune_parents(
+        &mut self,
+        coord: &Coord,
+        propagation: Propagation,
+    ) -> Result<(), Contradiction> {
+        let mut pending = vec![*coord];
+
+        while let Some(coord) = pending.pop() {
+            self.prune_coords(row_coords(coord.parent_row()), propagation, &mut pending)?;
+            self.prune_coords(col_coords(coord.parent_col()), propagation, &mut pending)?;
+            self.prune_coords(box_coords(coord.parent_box()), propagation, &mut pending)?;
         }
+
+        Ok(())
     }
 
-    fn has_sufficient_hints(&self) -> bool {
-        let mut count = 0;
+    /// Prune the house made up of `coords`, adding the cells that become
+    /// fixed to `fixed`.
+    fn prune_coords(
+        &mut self,
+        coords: [Coord; 9],
+        propagation: Propagation,
+        fixed: &mut Vec<Coord>,
+    ) -> Result<(), Contradiction> {
+        let mut house = coords.map(|coord| self.get(coord));
+        let mut result = prune_house(&mut house, &coords);
+        if result.is_ok() && propagation.hidden_singles {
+            result = fill_hidden_singles(&mut house, &coords);
+        }
+        if result.is_ok() && propagation.subsets {
+            result = eliminate_subsets(&mut house, &coords);
+        }
 
-        for cell in self.cells.iter() {
-            if let Cell::Fixed(_) = cell {
-                count += 1;
+        for (coord, cell) in coords.into_iter().zip(house) {
+            if let (Cell::Empty(_), Cell::Fixed(_)) = (self.get(coord), cell) {
+                fixed.push(coord);
             }
+            self.set(coord, cell);
         }
 
-        count >= 17
+        result
+    }
+
+    /// The number of filled cells.
+    pub fn clue_count(&self) -> usize {
+        self.cells
+            .iter()
+            .filter(|cell| matches!(cell, Cell::Fixed(_)))
+            .count()
+    }
+
+    pub(crate) fn has_sufficient_hints(&self) -> bool {
+        self.clue_count() >= 17
     }
 
-    fn is_legal(&self) -> bool {
+    pub(crate) fn is_legal(&self) -> bool {
         self.rows().all(|row| house_is_ok(&row))
             && self.cols().all(|col| house_is_ok(&col))
             && self.boxes().all(|box_| house_is_ok(&box_))
     }
 
-    fn number_is_legal(&self, coord: Coord, num: Number) -> bool {
+    /// The empty cell with the fewest possible numbers, preferring the
+    /// first in reading order on ties, or `None` if the grid is full.
+    fn min_remaining_cell(&self) -> Option<Coord> {
+        let mut best: Option<(Coord, u32)> = None;
+
+        for (index, cell) in self.cells.iter().enumerate() {
+            if let Cell::Empty(possible_nums) = cell {
+                let len = possible_nums.len();
+                if best.is_none_or(|(_, best_len)| len < best_len) {
+                    let coord = Coord { index: index as u8 };
+                    if len <= 1 {
+                        return Some(coord);
+                    }
+                    best = Some((coord, len));
+                }
+            }
+        }
+
+        best.map(|(coord, _)| coord)
+    }
+
+    pub(crate) fn number_is_legal(&self, coord: Coord, num: Number) -> bool {
         let current_cell = self.get(coord);
 
         match current_cell {
@@ -334,29 +616,94 @@ impl Grid {
 }
 
 /// Remove all fixed numbers in a house from the possible numbers
-/// of empty cells in the house.
-fn prune_house(house: &mut [Cell; 9]) {
-    let fixed_nums: Vec<Number> = house
-        .iter()
-        .filter_map(|cell| {
-            if let Cell::Fixed(num) = cell {
-                Some(*num)
-            } else {
-                None
+/// of empty cells in the house, fixing cells left with a single number.
+/// `coords` are the positions of the cells, used to report contradictions.
+fn prune_house(house: &mut [Cell; 9], coords: &[Coord; 9]) -> Result<(), Contradiction> {
+    let mut fixed = [false; 9];
+
+    for (cell, coord) in house.iter().zip(coords) {
+        if let Cell::Fixed(num) = cell {
+            let seen = &mut fixed[num.to_u8() as usize - 1];
+            if *seen {
+                return Err(Contradiction::Duplicate(*coord));
             }
-        })
-        .collect();
+            *seen = true;
+        }
+    }
 
-    for cell in house.iter_mut() {
+    let pruned = fixed;
+    for (cell, coord) in house.iter_mut().zip(coords) {
         if let Cell::Empty(possible_nums) = cell {
-            for num in fixed_nums.iter() {
-                possible_nums.remove(*num);
+            for (num, seen) in Number::ARRAY_ALL.into_iter().zip(pruned) {
+                if seen {
+                    possible_nums.remove(num);
+                }
+            }
+
+            if possible_nums.is_empty() {
+                return Err(Contradiction::NoCandidates(*coord));
             }
             if let Some(num) = possible_nums.single() {
+                // Only another single from this pass can already hold it.
+                let seen = &mut fixed[num.to_u8() as usize - 1];
+                if *seen {
+                    return Err(Contradiction::Duplicate(*coord));
+                }
+                *seen = true;
                 *cell = Cell::Fixed(num);
             }
         }
     }
+
+    Ok(())
+}
+
+/// Fix each number that can only go in one empty cell of a house, failing
+/// if a number can't go anywhere. `coords` are the positions of the cells,
+/// used to report contradictions.
+fn fill_hidden_singles(house: &mut [Cell; 9], coords: &[Coord; 9]) -> Result<(), Contradiction> {
+    let original = *house;
+
+    for num in Number::ARRAY_ALL {
+        let mut places = original.iter().enumerate().filter(|(_, cell)| match cell {
+            Cell::Fixed(fixed) => *fixed == num,
+            Cell::Empty(possible_nums) => possible_nums.contains(num),
+        });
+
+        match (places.next(), places.next()) {
+            (None, _) => return Err(Contradiction::NoPlace(num, coords[0])),
+            (Some((index, Cell::Empty(_))), None) => {
+                if let Cell::Fixed(_) = house[index] {
+                    return Err(Contradiction::Overlap(coords[index]));
+                }
+                house[index] = Cell::Fixed(num);
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Remove the possible numbers ruled out by naked and hidden subsets, then
+/// prune the house again to fix any cells left with a single number.
+fn eliminate_subsets(house: &mut [Cell; 9], coords: &[Coord; 9]) -> Result<(), Contradiction> {
+    let steps = logic::house_subsets(house, coords);
+    if steps.is_empty() {
+        return Ok(());
+    }
+
+    for elimination in steps.iter().flat_map(|step| &step.eliminations) {
+        let index = coords
+            .iter()
+            .position(|coord| *coord == elimination.coord)
+            .expect("eliminations are inside the house");
+        if let Cell::Empty(possible_nums) = &mut house[index] {
+            possible_nums.remove(elimination.num);
+        }
+    }
+
+    prune_house(house, coords)
 }
 
 fn house_is_ok(house: &[Cell; 9]) -> bool {
@@ -370,7 +717,32 @@ fn house_is_ok(house: &[Cell; 9]) -> bool {
         }
     }
 
-    return true;
+    true
+}
+
+enum TokenError {
+    OutOfRange,
+    Invalid,
+}
+
+/// Parse a single CSV value into a cell.
+fn parse_csv_token(token: &str) -> Result<Cell, TokenError> {
+    if token.is_empty() {
+        return Ok(Cell::init_empty());
+    }
+
+    if !token.chars().all(|char| char.is_ascii_digit()) {
+        return Err(TokenError::Invalid);
+    }
+
+    match token.parse::<u8>() {
+        Ok(0) => Ok(Cell::init_empty()),
+        Ok(int) => match Number::from_u8(int) {
+            Some(num) => Ok(Cell::Fixed(num)),
+            None => Err(TokenError::OutOfRange),
+        },
+        Err(_) => Err(TokenError::OutOfRange),
+    }
 }
 
 fn normalize_index(index: u8) -> u8 {
@@ -381,25 +753,45 @@ fn normalize_index(index: u8) -> u8 {
     }
 }
 
-fn solve_helper(grid: &Grid, c: Coord) -> Result<Grid, SolveError> {
+/// `depth` is the number of guesses made to reach `grid`. Each guess on the
+/// path to the returned solution is counted in `stats.guessed`.
+fn solve_helper(
+    grid: &Grid,
+    c: Coord,
+    depth: usize,
+    propagation: Propagation,
+    stats: &mut SolveStats,
+) -> Result<Grid, SolveError> {
     match grid.get(c) {
         Cell::Fixed(_) => match c.next() {
-            Some(next_c) => solve_helper(grid, next_c),
+            Some(next_c) => solve_helper(grid, next_c, depth, propagation, stats),
             None => Ok(*grid),
         },
         Cell::Empty(possible_nums) => {
             for num in possible_nums.iter() {
                 if grid.number_is_legal(c, num) {
+                    stats.visit(depth + 1);
                     let mut new_grid = *grid;
                     new_grid.set(c, Cell::Fixed(num));
                     match c.next() {
                         Some(next_coord) => {
-                            new_grid.prune_parents(&c);
-                            if let Ok(solution) = solve_helper(&new_grid, next_coord) {
-                                return Ok(solution);
+                            stats.prune_parents_calls += 1;
+                            if new_grid.prune_parents(&c, propagation).is_ok() {
+                                if let Ok(solution) = solve_helper(
+                                    &new_grid,
+                                    next_coord,
+                                    depth + 1,
+                                    propagation,
+                                    stats,
+                                ) {
+                                    stats.guessed += 1;
+                                    return Ok(solution);
+                                }
                             }
+                            stats.backtracks += 1;
                         }
                         None => {
+                            stats.guessed += 1;
                             return Ok(new_grid);
                         }
                     }
@@ -410,15 +802,325 @@ fn solve_helper(grid: &Grid, c: Coord) -> Result<Grid, SolveError> {
     }
 }
 
+/// Like `solve_helper`, but always branches on the empty cell with the
+/// fewest possible numbers instead of the next one in reading order.
+fn solve_helper_mrv(
+    grid: &Grid,
+    depth: usize,
+    propagation: Propagation,
+    stats: &mut SolveStats,
+) -> Result<Grid, SolveError> {
+    let Some(c) = grid.min_remaining_cell() else {
+        return Ok(*grid);
+    };
+
+    if let Cell::Empty(possible_nums) = grid.get(c) {
+        for num in possible_nums.iter() {
+            if grid.number_is_legal(c, num) {
+                stats.visit(depth + 1);
+                let mut new_grid = *grid;
+                new_grid.set(c, Cell::Fixed(num));
+                stats.prune_parents_calls += 1;
+                if new_grid.prune_parents(&c, propagation).is_ok() {
+                    if let Ok(solution) = solve_helper_mrv(&new_grid, depth + 1, propagation, stats)
+                    {
+                        stats.guessed += 1;
+                        return Ok(solution);
+                    }
+                }
+                stats.backtracks += 1;
+            }
+        }
+    }
+
+    Err(SolveError::NoSolutionFound)
+}
+
+/// How the backtracking search picks the next empty cell to branch on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Branching {
+    /// The next empty cell in reading order
+    Linear,
+    /// The empty cell with the fewest possible numbers
+    #[value(name = "mrv")]
+    MinRemaining,
+}
+
+/// Deductions made by propagation on top of removing fixed numbers from
+/// the possible numbers of their houses and fixing naked singles.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Propagation {
+    /// Fix a number in a cell when it is the only place left for it in a
+    /// row, column or box.
+    pub hidden_singles: bool,
+    /// Remove possible numbers using naked and hidden pairs, triples and
+    /// quads in each row, column and box.
+    pub subsets: bool,
+    /// Remove possible numbers locked into the intersection of a box with a
+    /// row or column.
+    pub intersections: bool,
+}
+
+/// Iterator over the solutions of a grid, created by `Grid::solutions`.
+pub struct Solutions {
+    stack: Vec<SearchFrame>,
+    /// A solution found while setting up the search, not yet returned.
+    pending: Option<Grid>,
+    branching: Branching,
+    propagation: Propagation,
+    stats: SolveStats,
+}
+impl Solutions {
+    /// What the search has done so far.
+    pub fn stats(&self) -> SolveStats {
+        self.stats
+    }
+
+    /// Push a frame that branches on the next empty cell: the first at or
+    /// after `c` when branching linearly, or the one with the fewest
+    /// possible numbers. A grid with no empty cells left is a solution.
+    fn descend(&mut self, grid: Grid, c: Coord) {
+        if self.branching == Branching::MinRemaining {
+            match grid.min_remaining_cell() {
+                Some(coord) => self.push(grid, coord),
+                None => self.pending = Some(grid),
+            }
+            return;
+        }
+
+        let mut coord = c;
+
+        loop {
+            match grid.get(coord) {
+                Cell::Fixed(_) => match coord.next() {
+                    Some(next) => coord = next,
+                    None => {
+                        self.pending = Some(grid);
+                        return;
+                    }
+                },
+                Cell::Empty(_) => {
+                    self.push(grid, coord);
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Push a frame that branches on the empty cell at `coord`.
+    fn push(&mut self, grid: Grid, coord: Coord) {
+        if let Cell::Empty(possible_nums) = grid.get(coord) {
+            self.stack.push(SearchFrame {
+                grid,
+                coord,
+                candidates: possible_nums.iter(),
+            });
+        }
+    }
+}
+impl Iterator for Solutions {
+    type Item = Grid;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(solution) = self.pending.take() {
+            return Some(solution);
+        }
+
+        loop {
+            let depth = self.stack.len();
+            let frame = self.stack.last_mut()?;
+            let Some(num) = frame.candidates.next() else {
+                // Undo the guess that led to this cell, if there was one.
+                self.stack.pop();
+                if !self.stack.is_empty() {
+                    self.stats.backtracks += 1;
+                }
+                continue;
+            };
+
+            if frame.grid.number_is_legal(frame.coord, num) {
+                self.stats.visit(depth);
+                let coord = frame.coord;
+                let mut new_grid = frame.grid;
+                new_grid.set(coord, Cell::Fixed(num));
+
+                // Only linear branching knows every cell is fixed after the
+                // last one.
+                let next = match self.branching {
+                    Branching::Linear => coord.next(),
+                    Branching::MinRemaining => Some(coord),
+                };
+                match next {
+                    Some(next_coord) => {
+                        self.stats.prune_parents_calls += 1;
+                        if new_grid.prune_parents(&coord, self.propagation).is_err() {
+                            self.stats.backtracks += 1;
+                            continue;
+                        }
+                        self.descend(new_grid, next_coord);
+                        if let Some(solution) = self.pending.take() {
+                            return Some(solution);
+                        }
+                    }
+                    None => return Some(new_grid),
+                }
+            }
+        }
+    }
+}
+
+/// An empty cell being branched on, with the candidates not yet tried.
+struct SearchFrame {
+    grid: Grid,
+    coord: Coord,
+    candidates: PossibleNumsIterator,
+}
+
+/// What a search did while solving a puzzle.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SolveStats {
+    /// Numbers tentatively placed in a cell.
+    pub nodes: u64,
+    /// Guesses that were undone because they led to a dead end.
+    pub backtracks: u64,
+    /// The most guesses made on a single search path.
+    pub max_depth: usize,
+    /// Passes of `Grid::prune_with` over the whole grid.
+    pub prune_calls: u64,
+    pub prune_parents_calls: u64,
+    /// Cells of the solution that were empty in the puzzle and filled by
+    /// propagation.
+    pub propagated: usize,
+    /// Cells of the solution that were empty in the puzzle and filled by
+    /// guessing.
+    pub guessed: usize,
+}
+impl SolveStats {
+    /// Add the stats of another search to these.
+    pub fn add(&mut self, other: SolveStats) {
+        self.nodes += other.nodes;
+        self.backtracks += other.backtracks;
+        self.max_depth = self.max_depth.max(other.max_depth);
+        self.prune_calls += other.prune_calls;
+        self.prune_parents_calls += other.prune_parents_calls;
+        self.propagated += other.propagated;
+        self.guessed += other.guessed;
+    }
+
+    /// Count a node `depth` guesses deep.
+    fn visit(&mut self, depth: usize) {
+        self.nodes += 1;
+        self.max_depth = self.max_depth.max(depth);
+    }
+}
+
 // Error enums
 
+/// A state propagation found that cannot lead to a solution.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Contradiction {
+    /// An empty cell has no possible numbers left.
+    NoCandidates(Coord),
+    /// A number is fixed in this cell and in another cell of the same house.
+    Duplicate(Coord),
+    /// This is the only place left in a house for two different numbers.
+    Overlap(Coord),
+    /// A number has no place left in the house that starts at this cell.
+    NoPlace(Number, Coord),
+}
+impl Contradiction {
+    pub fn coord(&self) -> Coord {
+        match self {
+            Self::NoCandidates(coord)
+            | Self::Duplicate(coord)
+            | Self::Overlap(coord)
+            | Self::NoPlace(_, coord) => *coord,
+        }
+    }
+}
+impl fmt::Display for Contradiction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let coord = self.coord();
+        let (row, col) = (coord.parent_row() + 1, coord.parent_col() + 1);
+        match self {
+            Self::NoCandidates(_) => write!(f, "no possible numbers at row {}, col {}", row, col),
+            Self::Duplicate(_) => write!(f, "duplicate number at row {}, col {}", row, col),
+            Self::Overlap(_) => write!(f, "two numbers need row {}, col {}", row, col),
+            Self::NoPlace(num, _) => write!(
+                f,
+                "no place left for {} in the house starting at row {}, col {}",
+                num.to_char(),
+                row,
+                col
+            ),
+        }
+    }
+}
+impl std::error::Error for Contradiction {}
+
 #[derive(Debug)]
 pub enum SolveError {
     TooFewHints,
     IllegalPuzzle,
     NoSolutionFound,
+    MultipleSolutions,
 }
 
+/// Errors produced while parsing a grid. Rows and columns are displayed
+/// numbered from 1 to 9, as they appear in the input. The fields hold them
+/// numbered from 0 to 8, as in `Coord`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// The input did not contain exactly 9 rows.
+    WrongRowCount { count: usize },
+    /// A row did not contain exactly 9 values.
+    WrongRowLength { row: usize, len: usize },
+    /// A single-line puzzle did not contain exactly 81 characters.
+    WrongLineLength { len: usize },
+    /// A value was a number, but not one from 0 to 9.
+    ValueOutOfRange {
+        row: usize,
+        col: usize,
+        token: String,
+    },
+    /// A value was not a number at all.
+    InvalidToken {
+        row: usize,
+        col: usize,
+        token: String,
+    },
+}
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::WrongRowCount { count } => {
+                write!(f, "expected 9 rows, found {}", count)
+            }
+            Self::WrongRowLength { row, len } => {
+                write!(f, "row {}: expected 9 values, found {}", row + 1, len)
+            }
+            Self::WrongLineLength { len } => {
+                write!(f, "expected 81 characters, found {}", len)
+            }
+            Self::ValueOutOfRange { row, col, token } => write!(
+                f,
+                "row {}, column {}: value {:?} is out of range",
+                row + 1,
+                col + 1,
+                token
+            ),
+            Self::InvalidToken { row, col, token } => write!(
+                f,
+                "row {}, column {}: invalid value {:?}",
+                row + 1,
+                col + 1,
+                token
+            ),
+        }
+    }
+}
+impl std::error::Error for ParseError {}
+
 pub struct RowIterator<'a> {
     grid: &'a Grid,
     row: u8,
@@ -493,17 +1195,17 @@ impl<'a> Iterator for BoxIterator<'a> {
     }
 }
 
-fn row_coords(row: u8) -> [Coord; 9] {
+pub(crate) fn row_coords(row: u8) -> [Coord; 9] {
     let row_index = normalize_index(row);
     array::from_fn(|col| Coord::from_row_col(row_index, col as u8))
 }
 
-fn col_coords(col: u8) -> [Coord; 9] {
+pub(crate) fn col_coords(col: u8) -> [Coord; 9] {
     let col_index = normalize_index(col);
     array::from_fn(|row| Coord::from_row_col(row as u8, col_index))
 }
 
-fn box_coords(box_: u8) -> [Coord; 9] {
+pub(crate) fn box_coords(box_: u8) -> [Coord; 9] {
     let box_index = normalize_index(box_);
     let row_start = (box_index / 3) * 3;
     let col_start = (box_index % 3) * 3;
@@ -527,8 +1229,8 @@ mod tests {
     }
 
     #[test]
-    fn test_from_csv_str() {
-        let grid = Grid::from_csv_str(puzzles::OK);
+    fn test_try_from_csv_str() {
+        let grid = Grid::try_from_csv_str(puzzles::OK).unwrap();
 
         assert_eq!(
             grid.get(Coord::from_row_col(3, 7)),
@@ -540,6 +1242,268 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_try_from_csv_str_errors() {
+        let short = puzzles::OK.replacen("0,0,0,0,0,2,1,0,4\n", "", 1);
+        assert_eq!(
+            Grid::try_from_csv_str(&short),
+            Err(ParseError::WrongRowCount { count: 8 })
+        );
+
+        let long_row = puzzles::OK.replacen("0,0,8,", "0,0,0,8,", 1);
+        assert_eq!(
+            Grid::try_from_csv_str(&long_row),
+            Err(ParseError::WrongRowLength { row: 1, len: 10 })
+        );
+
+        let bad_token = puzzles::OK.replacen("5,0,0", "5,x,0", 1);
+        assert_eq!(
+            Grid::try_from_csv_str(&bad_token),
+            Err(ParseError::InvalidToken {
+                row: 2,
+                col: 1,
+                token: String::from("x")
+            })
+        );
+
+        let out_of_range = puzzles::OK.replacen("0,9,0,0,8", "0,10,0,0,8", 1);
+        assert_eq!(
+            Grid::try_from_csv_str(&out_of_range),
+            Err(ParseError::ValueOutOfRange {
+                row: 3,
+                col: 1,
+                token: String::from("10")
+            })
+        );
+    }
+
+    #[test]
+    fn test_from_csv_str_lenient() {
+        let grid = Grid::from_csv_str_lenient("1,x,10\n2");
+
+        assert_eq!(
+            grid.get(Coord::from_row_col(0, 0)),
+            Cell::Fixed(Number::One)
+        );
+        assert_eq!(grid.get(Coord::from_row_col(0, 1)), Cell::init_empty());
+        assert_eq!(
+            grid.get(Coord::from_row_col(0, 2)),
+            Cell::Fixed(Number::One)
+        );
+        assert_eq!(
+            grid.get(Coord::from_row_col(0, 3)),
+            Cell::Fixed(Number::Two)
+        );
+        assert_eq!(grid.get(Coord::from_row_col(8, 8)), Cell::init_empty());
+    }
+
+    #[test]
+    fn test_line_str_round_trip() {
+        let line = concat!(
+            "000002104008001003500060090090080046600700000",
+            "100000080037200019000000030000090000"
+        );
+        let grid = Grid::try_from_line_str(line).unwrap();
+
+        assert_eq!(grid, Grid::try_from_csv_str(puzzles::OK).unwrap());
+        assert_eq!(grid.format_line(), line.replace('0', "."));
+
+        let blanks = line.replace('0', "_").replacen('_', "*", 1);
+        assert_eq!(Grid::try_from_line_str(&blanks), Ok(grid));
+    }
+
+    #[test]
+    fn test_try_from_line_str_errors() {
+        assert_eq!(
+            Grid::try_from_line_str("123"),
+            Err(ParseError::WrongLineLength { len: 3 })
+        );
+
+        let mut line = ".".repeat(81);
+        line.replace_range(10..11, "x");
+        assert_eq!(
+            Grid::try_from_line_str(&line),
+            Err(ParseError::InvalidToken {
+                row: 1,
+                col: 1,
+                token: String::from("x")
+            })
+        );
+    }
+
+    #[test]
+    fn test_solve_counting_nodes() {
+        let mut grid = Grid::try_from_csv_str(puzzles::OK).unwrap();
+        assert_eq!(grid.clue_count(), 24);
+
+        let mut nodes = 0;
+        grid.solve_counting_nodes(&mut nodes).unwrap();
+        assert!(nodes > 0);
+        assert_eq!(grid.clue_count(), 81);
+    }
+
+    #[test]
+    fn test_solve_with_stats() {
+        let puzzle = Grid::try_from_csv_str(puzzles::OK).unwrap();
+
+        for branching in [Branching::Linear, Branching::MinRemaining] {
+            let mut grid = puzzle;
+            let mut stats = SolveStats::default();
+            grid.solve_with_stats(branching, Propagation::default(), &mut stats)
+                .unwrap();
+
+            let mut nodes = 0;
+            let mut counted = puzzle;
+            counted.solve_with_branching(branching, &mut nodes).unwrap();
+            assert_eq!(stats.nodes, nodes);
+
+            assert_eq!(stats.propagated + stats.guessed, 81 - 24);
+            assert_eq!(stats.nodes, stats.guessed as u64 + stats.backtracks);
+            assert!(stats.guessed > 0);
+            assert!(stats.max_depth >= stats.guessed);
+            assert!(stats.prune_calls >= 1);
+            assert!(stats.prune_parents_calls <= stats.nodes);
+        }
+
+        // A single missing cell is filled by propagation alone.
+        let mut solved = puzzle;
+        solved.solve_strict().unwrap();
+        let mut grid = solved;
+        grid.set(Coord::FIRST, Cell::init_empty());
+
+        let mut stats = SolveStats::default();
+        grid.solve_with_stats(Branching::Linear, Propagation::default(), &mut stats)
+            .unwrap();
+        assert_eq!(grid, solved);
+        assert_eq!(stats.nodes, 0);
+        assert_eq!(stats.backtracks, 0);
+        assert_eq!(stats.propagated, 1);
+        assert_eq!(stats.guessed, 0);
+
+        let mut grid = puzzle;
+        let mut stats = SolveStats::default();
+        grid.solve_strict_with_stats(Branching::Linear, Propagation::default(), &mut stats)
+            .unwrap();
+        assert!(stats.nodes > 0);
+        assert!(stats.backtracks > 0);
+        assert!(stats.max_depth > 0);
+    }
+
+    #[test]
+    fn test_min_remaining_cell() {
+        let mut grid = Grid::try_from_csv_str(puzzles::OK).unwrap();
+        grid.prune().unwrap();
+
+        let coord = grid.min_remaining_cell().unwrap();
+        let Cell::Empty(best) = grid.get(coord) else {
+            panic!("expected an empty cell");
+        };
+        for cell in grid.cells.iter() {
+            if let Cell::Empty(possible_nums) = cell {
+                assert!(possible_nums.len() >= best.len());
+            }
+        }
+
+        let mut two = PossibleNums::ALL;
+        for num in Number::ARRAY_ALL.into_iter().skip(2) {
+            two.remove(num);
+        }
+        let mut grid = Grid::try_from_line_str(&".".repeat(81)).unwrap();
+        grid.set(Coord::from_row_col(4, 4), Cell::Empty(two));
+        grid.set(Coord::from_row_col(2, 7), Cell::Empty(two));
+        assert_eq!(grid.min_remaining_cell(), Some(Coord::from_row_col(2, 7)));
+
+        assert!(matches!(grid.solve_strict(), Err(SolveError::TooFewHints)));
+        let full = Grid::try_from_csv_str(puzzles::OK)
+            .unwrap()
+            .solutions()
+            .next();
+        assert_eq!(full.unwrap().min_remaining_cell(), None);
+    }
+
+    #[test]
+    fn test_solve_mrv() {
+        let puzzle = Grid::try_from_csv_str(puzzles::OK).unwrap();
+
+        let mut linear = puzzle;
+        linear.solve_strict().unwrap();
+
+        let mut mrv = puzzle;
+        let mut nodes = 0;
+        mrv.solve_with_branching(Branching::MinRemaining, &mut nodes)
+            .unwrap();
+
+        assert_eq!(mrv, linear);
+        assert!(nodes > 0);
+    }
+
+    #[test]
+    fn test_count_solutions() {
+        let grid = Grid::try_from_csv_str(puzzles::OK).unwrap();
+        assert_eq!(grid.count_solutions(10), 1);
+        assert!(grid.is_unique());
+
+        let mut solved = grid;
+        solved.solve_strict().unwrap();
+        assert!(solved.is_legal());
+        assert_eq!(solved.count_solutions(10), 1);
+
+        let mut grid = solved;
+        grid.set(Coord::FIRST, Cell::init_empty());
+        assert_eq!(grid.count_solutions(10), 1);
+
+        let empty = Grid::try_from_line_str(&".".repeat(81)).unwrap();
+        assert_eq!(empty.count_solutions(5), 5);
+        assert!(!empty.is_unique());
+        assert_eq!(empty.count_solutions(0), 0);
+    }
+
+    #[test]
+    fn test_solutions() {
+        let grid = Grid::try_from_csv_str(puzzles::OK).unwrap();
+        let mut solutions = grid.solutions();
+        let solution = solutions.next().unwrap();
+
+        assert!(solution.is_legal());
+        assert_eq!(solution.clue_count(), 81);
+        assert_eq!(solutions.next(), None);
+        assert!(solutions.stats().nodes > 0);
+
+        let empty = Grid::try_from_line_str(&".".repeat(81)).unwrap();
+        let first: Vec<Grid> = empty.solutions().take(20).collect();
+
+        assert_eq!(first.len(), 20);
+        for (i, solution) in first.iter().enumerate() {
+            assert!(solution.is_legal());
+            assert_eq!(solution.clue_count(), 81);
+            assert!(!first[i + 1..].contains(solution));
+        }
+
+        let mut illegal = grid;
+        illegal.set(Coord::FIRST, Cell::Fixed(Number::Four));
+        assert_eq!(illegal.solutions().next(), None);
+    }
+
+    #[test]
+    fn test_solve_strict() {
+        let mut grid = Grid::try_from_csv_str(puzzles::OK).unwrap();
+        grid.solve_strict().unwrap();
+
+        // The first 3 rows of a solution are enough hints, but leave the
+        // remaining rows free to be rearranged.
+        for row in 3..9 {
+            for coord in row_coords(row) {
+                grid.set(coord, Cell::init_empty());
+            }
+        }
+
+        assert!(matches!(
+            grid.solve_strict(),
+            Err(SolveError::MultipleSolutions)
+        ));
+        assert!(grid.solve().is_ok());
+    }
+
     #[test]
     fn test_house_is_ok() {
         let ok = [
@@ -566,8 +1530,8 @@ mod tests {
             Cell::Fixed(Number::Two),
         ];
 
-        assert_eq!(house_is_ok(&ok), true);
-        assert_eq!(house_is_ok(&bad), false);
+        assert!(house_is_ok(&ok));
+        assert!(!house_is_ok(&bad));
     }
 
     #[test]
@@ -584,7 +1548,7 @@ mod tests {
             Cell::init_empty(),
         ];
 
-        prune_house(&mut house);
+        assert_eq!(prune_house(&mut house, &row_coords(0)), Ok(()));
 
         let mut expected_possible = PossibleNums::ALL;
         expected_possible.remove(Number::One);
@@ -602,6 +1566,222 @@ mod tests {
         assert_eq!(house[8], Cell::Empty(expected_possible));
     }
 
+    #[test]
+    fn test_prune_house_contradictions() {
+        let coords = col_coords(2);
+        let only = |num: Number| {
+            let mut possible_nums = PossibleNums::ALL;
+            for other in Number::ARRAY_ALL {
+                if other != num {
+                    possible_nums.remove(other);
+                }
+            }
+            Cell::Empty(possible_nums)
+        };
+
+        let mut house = [Cell::init_empty(); 9];
+        house[1] = Cell::Fixed(Number::Four);
+        house[6] = Cell::Fixed(Number::Four);
+        assert_eq!(
+            prune_house(&mut house, &coords),
+            Err(Contradiction::Duplicate(coords[6]))
+        );
+
+        let mut house = [Cell::init_empty(); 9];
+        house[0] = Cell::Fixed(Number::Seven);
+        house[5] = only(Number::Seven);
+        assert_eq!(
+            prune_house(&mut house, &coords),
+            Err(Contradiction::NoCandidates(coords[5]))
+        );
+
+        // Two cells left with the same single number.
+        let mut house = [Cell::init_empty(); 9];
+        house[3] = only(Number::Two);
+        house[8] = only(Number::Two);
+        assert_eq!(
+            prune_house(&mut house, &coords),
+            Err(Contradiction::Duplicate(coords[8]))
+        );
+    }
+
+    #[test]
+    fn test_fill_hidden_singles() {
+        let coords = box_coords(4);
+        let mut without_three = PossibleNums::ALL;
+        without_three.remove(Number::Three);
+
+        // Three can only go in the sixth cell.
+        let mut house = [Cell::Empty(without_three); 9];
+        house[0] = Cell::Fixed(Number::One);
+        house[5] = Cell::init_empty();
+        assert_eq!(fill_hidden_singles(&mut house, &coords), Ok(()));
+        assert_eq!(house[5], Cell::Fixed(Number::Three));
+        assert_eq!(house[6], Cell::Empty(without_three));
+
+        // Three and four can only both go in the sixth cell.
+        let mut without_four = without_three;
+        without_four.remove(Number::Four);
+        let mut house = [Cell::Empty(without_four); 9];
+        house[5] = Cell::init_empty();
+        assert_eq!(
+            fill_hidden_singles(&mut house, &coords),
+            Err(Contradiction::Overlap(coords[5]))
+        );
+
+        // Three has no place left at all.
+        let mut house = [Cell::Empty(without_three); 9];
+        house[0] = Cell::Fixed(Number::One);
+        let contradiction = fill_hidden_singles(&mut house, &coords).unwrap_err();
+        assert_eq!(
+            contradiction,
+            Contradiction::NoPlace(Number::Three, coords[0])
+        );
+        assert_eq!(
+            contradiction.to_string(),
+            "no place left for 3 in the house starting at row 4, col 4"
+        );
+    }
+
+    #[test]
+    fn test_prune_hidden_singles() {
+        let puzzle = Grid::try_from_csv_str(puzzles::OK).unwrap();
+        let hidden_singles = Propagation {
+            hidden_singles: true,
+            ..Propagation::default()
+        };
+
+        let mut naked = puzzle;
+        naked.prune().unwrap();
+        let mut hidden = puzzle;
+        hidden.prune_with(hidden_singles).unwrap();
+        assert!(hidden.clue_count() > naked.clue_count());
+
+        let mut solved = puzzle;
+        solved.solve_strict().unwrap();
+        for (cell, solved) in hidden.cells.iter().zip(solved.cells) {
+            if let Cell::Fixed(_) = cell {
+                assert_eq!(*cell, solved);
+            }
+        }
+
+        let mut stats = SolveStats::default();
+        let mut grid = puzzle;
+        grid.solve_with_stats(Branching::Linear, hidden_singles, &mut stats)
+            .unwrap();
+        assert_eq!(grid, solved);
+
+        let mut nodes = 0;
+        let mut naked = puzzle;
+        naked.solve_counting_nodes(&mut nodes).unwrap();
+        assert!(stats.nodes < nodes);
+    }
+
+    #[test]
+    fn test_prune_subsets() {
+        let puzzle = Grid::try_from_csv_str(puzzles::OK).unwrap();
+        let subsets = Propagation {
+            subsets: true,
+            ..Propagation::default()
+        };
+
+        let mut singles = puzzle;
+        singles.prune().unwrap();
+        let mut grid = puzzle;
+        grid.prune_with(subsets).unwrap();
+        assert_ne!(grid, singles);
+        assert!(logic::subsets(&grid).is_empty());
+
+        let mut solved = puzzle;
+        solved.solve_strict().unwrap();
+        for (cell, solved) in grid.cells.iter().zip(solved.cells) {
+            match (cell, solved) {
+                (Cell::Fixed(_), _) => assert_eq!(*cell, solved),
+                (Cell::Empty(possible_nums), Cell::Fixed(num)) => {
+                    assert!(possible_nums.contains(num))
+                }
+                _ => unreachable!(),
+            }
+        }
+
+        let mut stats = SolveStats::default();
+        let mut grid = puzzle;
+        grid.solve_with_stats(Branching::Linear, subsets, &mut stats)
+            .unwrap();
+        assert_eq!(grid, solved);
+    }
+
+    #[test]
+    fn test_prune_intersections() {
+        let puzzle = Grid::try_from_csv_str(puzzles::OK).unwrap();
+        let intersections = Propagation {
+            intersections: true,
+            ..Propagation::default()
+        };
+
+        let mut singles = puzzle;
+        singles.prune().unwrap();
+        let mut grid = puzzle;
+        grid.prune_with(intersections).unwrap();
+        assert_ne!(grid, singles);
+        assert!(logic::intersections(&grid).is_empty());
+
+        let mut solved = puzzle;
+        solved.solve_strict().unwrap();
+        let mut stats = SolveStats::default();
+        let mut grid = puzzle;
+        grid.solve_with_stats(Branching::Linear, intersections, &mut stats)
+            .unwrap();
+        assert_eq!(grid, solved);
+
+        // Hidden singles fix cells before their number is removed from the
+        // rest of the box, which must not look like a locked candidate.
+        let mut grid = Grid::try_from_line_str(
+            "900040000000600031020000090000700020002935600070002000060000073510009000000080009",
+        )
+        .unwrap();
+        let all = Propagation {
+            hidden_singles: true,
+            subsets: true,
+            intersections: true,
+        };
+        assert_eq!(grid.prune_with(all), Ok(()));
+    }
+
+    #[test]
+    fn test_prune_contradiction() {
+        let mut grid = Grid::try_from_csv_str(puzzles::OK).unwrap();
+        assert_eq!(grid.prune(), Ok(()));
+
+        // Row 0 holds 1 to 7 and column 0 holds 8 and 9, leaving nothing
+        // for the first cell.
+        let mut line = String::from(".1234567.");
+        line.push_str(&".".repeat(27));
+        line.push_str("8........");
+        line.push_str(&".".repeat(27));
+        line.push_str("9........");
+        let mut grid = Grid::try_from_line_str(&line).unwrap();
+        let contradiction = grid.prune().unwrap_err();
+        assert_eq!(contradiction, Contradiction::NoCandidates(Coord::FIRST));
+        assert_eq!(
+            contradiction.to_string(),
+            "no possible numbers at row 1, col 1"
+        );
+    }
+
+    #[test]
+    fn test_solve_is_legal() {
+        // Propagation used to fix the same number twice in a house, so the
+        // search could return a full grid that is not a solution.
+        let mut grid = Grid::try_from_csv_str(puzzles::OK).unwrap();
+        grid.solve().unwrap();
+        assert!(grid.is_legal());
+
+        let mut strict = Grid::try_from_csv_str(puzzles::OK).unwrap();
+        strict.solve_strict().unwrap();
+        assert_eq!(grid, strict);
+    }
+
     #[test]
     fn test_row_coords() {
         let coords = row_coords(3);
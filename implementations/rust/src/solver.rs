@@ -0,0 +1,426 @@
+use crate::bitmask;
+use crate::dlx;
+use crate::explicit_stack;
+use crate::smart_backtrack::{Branching, Grid, Propagation, SolveError, SolveStats};
+use std::fmt;
+
+/// A sudoku solving algorithm that can be benchmarked.
+pub trait Solver {
+    /// The name used to select the solver on the command line.
+    fn name(&self) -> &'static str;
+
+    /// Solve the puzzle, adding what the search did to `stats`. What counts
+    /// as a node depends on the algorithm, and solvers that don't
+    /// `tracks_search_stats` only count nodes.
+    fn solve_with_stats(&self, grid: &mut Grid, stats: &mut SolveStats) -> Result<(), SolveError>;
+
+    /// Like `solve_with_stats`, but fails with
+    /// `SolveError::MultipleSolutions` if the puzzle does not have exactly
+    /// one solution.
+    fn solve_strict_with_stats(
+        &self,
+        grid: &mut Grid,
+        stats: &mut SolveStats,
+    ) -> Result<(), SolveError>;
+
+    /// Count the solutions of the puzzle, stopping once `limit` have been
+    /// found.
+    fn count_solutions(&self, grid: &Grid, limit: usize) -> usize;
+
+    /// Whether the solver can pick cells to branch on other than in
+    /// reading order.
+    fn supports_branching(&self) -> bool {
+        false
+    }
+
+    /// Whether the solver propagates with the deductions in `Propagation`.
+    fn supports_propagation(&self) -> bool {
+        false
+    }
+
+    /// Whether the solver fills in every field of `SolveStats`, rather than
+    /// only `nodes`.
+    fn tracks_search_stats(&self) -> bool {
+        false
+    }
+
+    fn solve(&self, grid: &mut Grid) -> Result<(), SolveError> {
+        self.solve_with_stats(grid, &mut SolveStats::default())
+    }
+
+    fn solve_strict(&self, grid: &mut Grid) -> Result<(), SolveError> {
+        self.solve_strict_with_stats(grid, &mut SolveStats::default())
+    }
+
+    fn is_unique(&self, grid: &Grid) -> bool {
+        self.count_solutions(grid, 2) == 1
+    }
+}
+
+/// The names of every registered solver, in registry order.
+pub const NAMES: [&str; 4] = ["smart-backtrack", "explicit-stack", "dlx", "bitmask"];
+
+/// Every available solver. `branching` configures the smart-backtrack
+/// solver, and `propagation` the smart-backtrack and explicit-stack solvers.
+pub fn registry(branching: Branching, propagation: Propagation) -> Vec<Box<dyn Solver>> {
+    vec![
+        Box::new(SmartBacktrack {
+            branching,
+            propagation,
+        }),
+        Box::new(ExplicitStack { propagation }),
+        Box::new(Dlx),
+        Box::new(Bitmask),
+    ]
+}
+
+/// Why `select` could not pick the solvers asked for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SelectError<'a> {
+    /// No solver has this name.
+    Unknown(&'a str),
+    /// The solver was named more than once.
+    Repeated(&'a str),
+    /// The solver can't use the command line option it was given.
+    Unsupported {
+        solver: &'static str,
+        option: &'static str,
+    },
+}
+impl fmt::Display for SelectError<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Unknown(name) => write!(f, "unknown solver {}", name),
+            Self::Repeated(name) => write!(f, "solver {} was selected more than once", name),
+            Self::Unsupported { solver, option } => {
+                write!(f, "the {} solver does not support {}", solver, option)
+            }
+        }
+    }
+}
+impl std::error::Error for SelectError<'_> {}
+
+/// Look up registered solvers by name, keeping the order of `names`. Fails
+/// on the first unknown or repeated name, or on the first solver that
+/// can't use a non-default `branching` or `propagation`.
+pub fn select(
+    names: &[String],
+    branching: Branching,
+    propagation: Propagation,
+) -> Result<Vec<Box<dyn Solver>>, SelectError<'_>> {
+    let mut registry: Vec<(&str, Option<Box<dyn Solver>>)> = registry(branching, propagation)
+        .into_iter()
+        .map(|solver| (solver.name(), Some(solver)))
+        .collect();
+
+    names
+        .iter()
+        .map(|name| {
+            let (_, slot) = registry
+                .iter_mut()
+                .find(|(registered, _)| registered == name)
+                .ok_or(SelectError::Unknown(name))?;
+            let solver = slot.take().ok_or(SelectError::Repeated(name))?;
+
+            let unsupported = |option| SelectError::Unsupported {
+                solver: solver.name(),
+                option,
+            };
+            if branching != Branching::Linear && !solver.supports_branching() {
+                return Err(unsupported("--branching"));
+            }
+            if !solver.supports_propagation() {
+                for (enabled, option) in [
+                    (propagation.hidden_singles, "--hidden-singles"),
+                    (propagation.subsets, "--subsets"),
+                    (propagation.intersections, "--intersections"),
+                ] {
+                    if enabled {
+                        return Err(unsupported(option));
+                    }
+                }
+            }
+
+            Ok(solver)
+        })
+        .collect()
+}
+
+/// Recursive backtracking with constraint propagation.
+pub struct SmartBacktrack {
+    pub branching: Branching,
+    pub propagation: Propagation,
+}
+impl Solver for SmartBacktrack {
+    fn name(&self) -> &'static str {
+        "smart-backtrack"
+    }
+
+    fn supports_branching(&self) -> bool {
+        true
+    }
+
+    fn supports_propagation(&self) -> bool {
+        true
+    }
+
+    fn tracks_search_stats(&self) -> bool {
+        true
+    }
+
+    fn solve_with_stats(&self, grid: &mut Grid, stats: &mut SolveStats) -> Result<(), SolveError> {
+        grid.solve_with_stats(self.branching, self.propagation, stats)
+    }
+
+    fn solve_strict_with_stats(
+        &self,
+        grid: &mut Grid,
+        stats: &mut SolveStats,
+    ) -> Result<(), SolveError> {
+        grid.solve_strict_with_stats(self.branching, self.propagation, stats)
+    }
+
+    fn count_solutions(&self, grid: &Grid, limit: usize) -> usize {
+        grid.solutions_with(self.branching, self.propagation)
+            .take(limit)
+            .count()
+    }
+}
+
+/// The smart-backtrack search, using an explicit stack instead of recursion.
+pub struct ExplicitStack {
+    pub propagation: Propagation,
+}
+impl Solver for ExplicitStack {
+    fn name(&self) -> &'static str {
+        "explicit-stack"
+    }
+
+    fn supports_propagation(&self) -> bool {
+        true
+    }
+
+    fn solve_with_stats(&self, grid: &mut Grid, stats: &mut SolveStats) -> Result<(), SolveError> {
+        explicit_stack::solve_counting_nodes(grid, self.propagation, &mut stats.nodes)
+    }
+
+    fn solve_strict_with_stats(
+        &self,
+        grid: &mut Grid,
+        stats: &mut SolveStats,
+    ) -> Result<(), SolveError> {
+        explicit_stack::solve_strict_counting_nodes(grid, self.propagation, &mut stats.nodes)
+    }
+
+    fn count_solutions(&self, grid: &Grid, limit: usize) -> usize {
+        explicit_stack::count_solutions(grid, self.propagation, limit)
+    }
+
+    fn solve(&self, grid: &mut Grid) -> Result<(), SolveError> {
+        explicit_stack::solve(grid, self.propagation)
+    }
+}
+
+/// Dancing Links (Algorithm X) over the exact-cover matrix.
+pub struct Dlx;
+impl Solver for Dlx {
+    fn name(&self) -> &'static str {
+        "dlx"
+    }
+
+    fn solve_with_stats(&self, grid: &mut Grid, stats: &mut SolveStats) -> Result<(), SolveError> {
+        dlx::solve_counting_nodes(grid, &mut stats.nodes)
+    }
+
+    fn solve_strict_with_stats(
+        &self,
+        grid: &mut Grid,
+        stats: &mut SolveStats,
+    ) -> Result<(), SolveError> {
+        dlx::solve_strict_counting_nodes(grid, &mut stats.nodes)
+    }
+
+    fn count_solutions(&self, grid: &Grid, limit: usize) -> usize {
+        dlx::count_solutions(grid, limit)
+    }
+
+    fn solve(&self, grid: &mut Grid) -> Result<(), SolveError> {
+        dlx::solve(grid)
+    }
+}
+
+/// Backtracking over row, column and box occupancy bitmasks.
+pub struct Bitmask;
+impl Solver for Bitmask {
+    fn name(&self) -> &'static str {
+        "bitmask"
+    }
+
+    fn solve_with_stats(&self, grid: &mut Grid, stats: &mut SolveStats) -> Result<(), SolveError> {
+        bitmask::solve_counting_nodes(grid, &mut stats.nodes)
+    }
+
+    fn solve_strict_with_stats(
+        &self,
+        grid: &mut Grid,
+        stats: &mut SolveStats,
+    ) -> Result<(), SolveError> {
+        bitmask::solve_strict_counting_nodes(grid, &mut stats.nodes)
+    }
+
+    fn count_solutions(&self, grid: &Grid, limit: usize) -> usize {
+        bitmask::count_solutions(grid, limit)
+    }
+
+    fn solve(&self, grid: &mut Grid) -> Result<(), SolveError> {
+        bitmask::solve(grid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::puzzles;
+
+    #[test]
+    fn test_registry_names() {
+        let names: Vec<&str> = registry(Branching::Linear, Propagation::default())
+            .iter()
+            .map(|solver| solver.name())
+            .collect();
+        assert_eq!(names, NAMES);
+    }
+
+    #[test]
+    fn test_search_stats() {
+        let solvers = registry(Branching::Linear, Propagation::default());
+        let tracked: Vec<&str> = solvers
+            .iter()
+            .filter(|solver| solver.tracks_search_stats())
+            .map(|solver| solver.name())
+            .collect();
+        assert_eq!(tracked, ["smart-backtrack"]);
+
+        // The others leave everything but the node count untouched.
+        let puzzle = Grid::try_from_csv_str(puzzles::OK).unwrap();
+        for solver in &solvers[1..] {
+            let mut grid = puzzle;
+            let mut stats = SolveStats::default();
+            solver.solve_with_stats(&mut grid, &mut stats).unwrap();
+            assert_eq!(
+                stats,
+                SolveStats {
+                    nodes: stats.nodes,
+                    ..SolveStats::default()
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn test_select() {
+        let names = vec![String::from("dlx"), String::from("smart-backtrack")];
+        let solvers = select(&names, Branching::Linear, Propagation::default()).unwrap();
+        let selected: Vec<&str> = solvers.iter().map(|solver| solver.name()).collect();
+        assert_eq!(selected, ["dlx", "smart-backtrack"]);
+
+        let names = vec![String::from("dlx"), String::from("nope")];
+        assert_eq!(
+            select(&names, Branching::Linear, Propagation::default()).err(),
+            Some(SelectError::Unknown("nope"))
+        );
+
+        // Each solver can only be selected once.
+        let names = vec![String::from("dlx"), String::from("dlx")];
+        assert_eq!(
+            select(&names, Branching::Linear, Propagation::default()).err(),
+            Some(SelectError::Repeated("dlx"))
+        );
+    }
+
+    #[test]
+    fn test_select_unsupported() {
+        let hidden_singles = Propagation {
+            hidden_singles: true,
+            ..Propagation::default()
+        };
+        let names = vec![String::from("smart-backtrack"), String::from("dlx")];
+        assert_eq!(
+            select(&names, Branching::MinRemaining, Propagation::default()).err(),
+            Some(SelectError::Unsupported {
+                solver: "dlx",
+                option: "--branching"
+            })
+        );
+
+        let names = vec![String::from("explicit-stack")];
+        assert!(select(&names, Branching::Linear, hidden_singles).is_ok());
+        assert!(select(&names, Branching::MinRemaining, hidden_singles).is_err());
+
+        let names = vec![String::from("bitmask")];
+        let error = select(&names, Branching::Linear, hidden_singles).err();
+        assert_eq!(
+            error.unwrap().to_string(),
+            "the bitmask solver does not support --hidden-singles"
+        );
+    }
+
+    #[test]
+    fn test_solvers_agree() {
+        let puzzle = Grid::try_from_csv_str(puzzles::OK).unwrap();
+        let mut expected = puzzle;
+        expected.solve_strict().unwrap();
+
+        for solver in registry(Branching::Linear, Propagation::default()) {
+            assert!(solver.is_unique(&puzzle), "{}", solver.name());
+
+            let mut grid = puzzle;
+            solver.solve_strict(&mut grid).unwrap();
+            assert_eq!(grid, expected, "{}", solver.name());
+        }
+    }
+
+    #[test]
+    fn test_smart_backtrack_settings() {
+        let puzzle = Grid::try_from_csv_str(puzzles::OK).unwrap();
+        let mut expected = puzzle;
+        expected.solve_strict().unwrap();
+        let several = Grid::try_from_line_str(&puzzle.format_line().replacen('9', ".", 3)).unwrap();
+        let several_count = several.count_solutions(100);
+        assert!(several_count > 1);
+
+        let propagation = Propagation {
+            hidden_singles: true,
+            subsets: true,
+            intersections: true,
+        };
+        let mut nodes = Vec::new();
+        for branching in [Branching::Linear, Branching::MinRemaining] {
+            for propagation in [Propagation::default(), propagation] {
+                let solver = SmartBacktrack {
+                    branching,
+                    propagation,
+                };
+                assert!(solver.is_unique(&puzzle));
+                assert_eq!(solver.count_solutions(&several, 100), several_count);
+
+                let mut grid = puzzle;
+                solver.solve_strict(&mut grid).unwrap();
+                assert_eq!(grid, expected);
+
+                let mut grid = several;
+                let mut stats = SolveStats::default();
+                assert!(matches!(
+                    solver.solve_strict_with_stats(&mut grid, &mut stats),
+                    Err(SolveError::MultipleSolutions)
+                ));
+                nodes.push(stats.nodes);
+            }
+        }
+
+        // Each setting takes the strict search down a different path.
+        nodes.sort();
+        nodes.dedup();
+        assert_eq!(nodes.len(), 4);
+    }
+}
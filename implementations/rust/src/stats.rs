@@ -0,0 +1,101 @@
+use std::fmt;
+use std::time::Duration;
+
+/// Summary statistics over a set of per-puzzle solve times.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Summary {
+    pub count: usize,
+    pub total: Duration,
+    pub mean: Duration,
+    pub median: Duration,
+    pub p90: Duration,
+    pub p99: Duration,
+    pub min: Duration,
+    pub max: Duration,
+}
+impl Summary {
+    /// Summarize a set of timings, or return `None` if there are none.
+    pub fn from_durations(durations: &[Duration]) -> Option<Self> {
+        if durations.is_empty() {
+            return None;
+        }
+
+        let mut sorted = durations.to_vec();
+        sorted.sort_unstable();
+
+        let count = sorted.len();
+        let total: Duration = sorted.iter().sum();
+
+        Some(Self {
+            count,
+            total,
+            mean: total / count as u32,
+            median: percentile(&sorted, 50),
+            p90: percentile(&sorted, 90),
+            p99: percentile(&sorted, 99),
+            min: sorted[0],
+            max: sorted[count - 1],
+        })
+    }
+
+    /// Throughput, based on the total of the measured times.
+    pub fn per_second(&self) -> f64 {
+        self.count as f64 / self.total.as_secs_f64()
+    }
+}
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "puzzles:    {}", self.count)?;
+        writeln!(f, "total:      {:.3?}", self.total)?;
+        writeln!(f, "mean:       {:.3?}", self.mean)?;
+        writeln!(f, "median:     {:.3?}", self.median)?;
+        writeln!(f, "p90:        {:.3?}", self.p90)?;
+        writeln!(f, "p99:        {:.3?}", self.p99)?;
+        writeln!(f, "min:        {:.3?}", self.min)?;
+        writeln!(f, "max:        {:.3?}", self.max)?;
+        write!(f, "puzzles/s:  {:.1}", self.per_second())
+    }
+}
+
+/// Nearest-rank percentile of an already sorted, non-empty slice.
+fn percentile(sorted: &[Duration], pct: usize) -> Duration {
+    let rank = (pct * sorted.len()).div_ceil(100);
+    sorted[rank.saturating_sub(1)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn millis(values: &[u64]) -> Vec<Duration> {
+        values.iter().map(|ms| Duration::from_millis(*ms)).collect()
+    }
+
+    #[test]
+    fn test_summary() {
+        let durations = millis(&[7, 1, 10, 3, 5, 2, 9, 4, 8, 6]);
+        let summary = Summary::from_durations(&durations).unwrap();
+
+        assert_eq!(summary.count, 10);
+        assert_eq!(summary.total, Duration::from_millis(55));
+        assert_eq!(summary.mean, Duration::from_micros(5500));
+        assert_eq!(summary.median, Duration::from_millis(5));
+        assert_eq!(summary.p90, Duration::from_millis(9));
+        assert_eq!(summary.p99, Duration::from_millis(10));
+        assert_eq!(summary.min, Duration::from_millis(1));
+        assert_eq!(summary.max, Duration::from_millis(10));
+        assert!((summary.per_second() - 10.0 / 0.055).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_summary_empty() {
+        assert_eq!(Summary::from_durations(&[]), None);
+    }
+
+    #[test]
+    fn test_percentile_single() {
+        let durations = millis(&[4]);
+        assert_eq!(percentile(&durations, 1), Duration::from_millis(4));
+        assert_eq!(percentile(&durations, 99), Duration::from_millis(4));
+    }
+}